@@ -0,0 +1,36 @@
+use crate::tokentype::TokenType;
+
+/// Functions every program can call without declaring them. A function the
+/// program declares with the same name takes their place.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Builtin {
+    /// `len(string s) -> int`, the length of `s` in bytes
+    Len,
+    /// `substring(string s, int start, int end) -> string`, the bytes of `s`
+    /// from `start` up to but not including `end`
+    Substring,
+}
+
+impl Builtin {
+    pub fn lookup(name: &str) -> Option<Self> {
+        match name {
+            "len" => Some(Self::Len),
+            "substring" => Some(Self::Substring),
+            _ => None,
+        }
+    }
+
+    pub fn params(self) -> &'static [TokenType] {
+        match self {
+            Self::Len => &[TokenType::STRING],
+            Self::Substring => &[TokenType::STRING, TokenType::INT, TokenType::INT],
+        }
+    }
+
+    pub fn return_type(self) -> TokenType {
+        match self {
+            Self::Len => TokenType::INT,
+            Self::Substring => TokenType::STRING,
+        }
+    }
+}
@@ -0,0 +1,915 @@
+use std::collections::HashMap;
+
+use crate::{
+    builtin::Builtin,
+    diagnostic::{
+        ARITY_MISMATCH, DIVISION_BY_ZERO, DUPLICATE_DEFINITION, Diagnostic, INVALID_CAST,
+        INVALID_CONTROL_FLOW, INVALID_OPERAND, TYPE_MISMATCH, UNDEFINED_NAME,
+    },
+    expr::{Expr, LiteralValue},
+    stmt::Stmt,
+    token::{Span, Token},
+    tokentype::TokenType,
+};
+
+// Types are the same TokenTypes the Compiler uses: INT, FLOAT, BOOL, STRING,
+// with NIL standing in for "no value" (void functions, missing initializers).
+#[derive(Clone)]
+pub struct Checker {
+    scopes: Vec<HashMap<String, TokenType>>,
+    functions: HashMap<String, (Vec<TokenType>, TokenType, Span)>,
+    // where each function of the program (or REPL entry) being checked is
+    // first defined, a later entry may define the same name again
+    defined: HashMap<String, Span>,
+    // return type and name of the function whose body is being checked
+    current_function: Option<(TokenType, Span)>,
+    loop_depth: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker {
+    pub fn new() -> Self {
+        Self {
+            scopes: vec![HashMap::new()],
+            functions: HashMap::new(),
+            defined: HashMap::new(),
+            current_function: None,
+            loop_depth: 0,
+            diagnostics: vec![],
+        }
+    }
+
+    // Returns every error and warning found, the program is only safe to
+    // compile when none of them is an error.
+    pub fn check(mut self, statements: &mut [Stmt]) -> Vec<Diagnostic> {
+        self.check_entry(statements)
+    }
+
+    // Checks statements that continue a program checked by earlier calls,
+    // the REPL uses this to keep what previous lines declared.
+    pub fn check_entry(&mut self, statements: &mut [Stmt]) -> Vec<Diagnostic> {
+        self.defined.clear();
+        // top level functions are visible before their definition, same as in the Compiler
+        for statement in statements.iter() {
+            if let Stmt::Function {
+                name,
+                params,
+                return_type,
+                ..
+            } = statement
+            {
+                // a duplicate is reported when its statement is checked
+                if !self.defined.contains_key(&name.lexeme) {
+                    self.defined.insert(name.lexeme.clone(), name.span);
+                    self.declare_function(name, params, *return_type);
+                }
+            }
+        }
+
+        self.check_block(statements);
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    fn check_block(&mut self, statements: &mut [Stmt]) {
+        let mut jumped: Option<Span> = None;
+        for statement in statements.iter_mut() {
+            if let Some(jump) = jumped.take() {
+                self.report(
+                    Diagnostic::warning(statement.span(), "unreachable statement")
+                        .with_secondary(jump, "any code following this is unreachable"),
+                );
+            }
+            self.check_statement(statement);
+            if let Stmt::Return { span, .. }
+            | Stmt::Break { span, .. }
+            | Stmt::Continue { span, .. } = statement
+            {
+                jumped = Some(*span);
+            }
+        }
+    }
+
+    fn check_statement(&mut self, st: &mut Stmt) {
+        match st {
+            Stmt::Var {
+                name,
+                data_type,
+                initializer,
+                ..
+            } => {
+                // `float x;` parses to a nil initializer and gets the zero value
+                let is_default = matches!(
+                    initializer,
+                    Expr::Literal {
+                        value: LiteralValue::Nil,
+                        ..
+                    }
+                );
+                let ty = self.check_expr(initializer);
+                if !is_default
+                    && let Some(ty) = self.coerce(initializer, ty, *data_type)
+                    && ty != *data_type
+                {
+                    self.report_mismatch(
+                        Diagnostic::error(
+                            initializer.span(),
+                            format!(
+                                "cannot initialize '{}' of type {} with a value of type {}",
+                                name.lexeme,
+                                type_name(*data_type),
+                                type_name(ty)
+                            ),
+                        )
+                        .with_code(TYPE_MISMATCH)
+                        .with_label(format!("expected {}", type_name(*data_type)))
+                        .with_secondary(name.span, "declared here"),
+                        ty,
+                        *data_type,
+                    );
+                }
+                self.declare(name, *data_type);
+            }
+            Stmt::Expression { expression, .. } => {
+                self.check_expr(expression);
+            }
+            Stmt::Print { expression, .. } => {
+                if let Some(TokenType::NIL) = self.check_expr(expression) {
+                    self.report(
+                        Diagnostic::error(expression.span(), "cannot print a value of type nil")
+                            .with_code(TYPE_MISMATCH)
+                            .with_label("this has no value"),
+                    );
+                }
+            }
+            Stmt::Block { stmts, .. } => {
+                self.scopes.push(HashMap::new());
+                self.check_block(stmts);
+                self.scopes.pop();
+            }
+            Stmt::IfElse {
+                condition,
+                then,
+                els,
+                ..
+            } => {
+                self.check_condition(condition, "if");
+                self.check_statement(then);
+                if let Some(els) = els {
+                    self.check_statement(els);
+                }
+            }
+            Stmt::WHILE {
+                condition,
+                block,
+                increment,
+                ..
+            } => {
+                self.check_condition(condition, "while");
+                self.loop_depth += 1;
+                self.check_statement(block);
+                self.loop_depth -= 1;
+                if let Some(increment) = increment {
+                    self.check_expr(increment);
+                }
+            }
+            Stmt::Function {
+                name,
+                params,
+                return_type,
+                body,
+                ..
+            } => {
+                match self.defined.get(&name.lexeme) {
+                    Some(first) if *first != name.span => {
+                        // the first definition keeps the name, the body is still checked
+                        let error = Diagnostic::error(
+                            name.span,
+                            format!("function '{}' is already defined", name.lexeme),
+                        )
+                        .with_code(DUPLICATE_DEFINITION)
+                        .with_label("redefined here")
+                        .with_secondary(*first, "first defined here")
+                        .with_help("rename one of the functions");
+                        self.report(error);
+                    }
+                    Some(_) => {}
+                    None => {
+                        self.defined.insert(name.lexeme.clone(), name.span);
+                        self.declare_function(name, params, *return_type);
+                    }
+                }
+
+                // function bodies can't see the locals of whoever declared them
+                let outer_scopes = std::mem::replace(&mut self.scopes, vec![HashMap::new()]);
+                let outer_function = self.current_function.replace((*return_type, name.span));
+                let outer_loop_depth = std::mem::take(&mut self.loop_depth);
+
+                for (param, param_type) in params {
+                    self.declare(param, *param_type);
+                }
+                self.check_block(body);
+
+                self.scopes = outer_scopes;
+                self.current_function = outer_function;
+                self.loop_depth = outer_loop_depth;
+            }
+            Stmt::Return { token, expr, .. } => {
+                let Some((expected, function)) = self.current_function else {
+                    self.report(
+                        Diagnostic::error(token.span, "cannot return from top-level code")
+                            .with_code(INVALID_CONTROL_FLOW),
+                    );
+                    if let Some(expr) = expr {
+                        self.check_expr(expr);
+                    }
+                    return;
+                };
+                match expr {
+                    Some(expr) => {
+                        let ty = self.check_expr(expr);
+                        match self.coerce(expr, ty, expected) {
+                            Some(_) if expected == TokenType::NIL => {
+                                self.report(
+                                    Diagnostic::error(
+                                        expr.span(),
+                                        "cannot return a value from a function without a return type",
+                                    )
+                                    .with_code(TYPE_MISMATCH)
+                                    .with_secondary(function, "function declared here")
+                                    .with_help("add a return type, e.g. `-> float`"),
+                                );
+                            }
+                            Some(ty) if ty != expected => {
+                                self.report_mismatch(
+                                    Diagnostic::error(
+                                        expr.span(),
+                                        format!(
+                                            "expected a return value of type {} but found {}",
+                                            type_name(expected),
+                                            type_name(ty)
+                                        ),
+                                    )
+                                    .with_code(TYPE_MISMATCH)
+                                    .with_label(format!("expected {}", type_name(expected)))
+                                    .with_secondary(function, "return type declared here"),
+                                    ty,
+                                    expected,
+                                );
+                            }
+                            _ => (),
+                        }
+                    }
+                    None if expected != TokenType::NIL => {
+                        self.report(
+                            Diagnostic::error(
+                                token.span,
+                                format!("expected a return value of type {}", type_name(expected)),
+                            )
+                            .with_code(TYPE_MISMATCH)
+                            .with_secondary(function, "return type declared here"),
+                        );
+                    }
+                    None => (),
+                }
+            }
+            Stmt::Break { token, .. } | Stmt::Continue { token, .. } => {
+                if self.loop_depth == 0 {
+                    self.report(
+                        Diagnostic::error(
+                            token.span,
+                            format!("'{}' outside of a loop", token.lexeme),
+                        )
+                        .with_code(INVALID_CONTROL_FLOW),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_condition(&mut self, condition: &mut Expr, kind: &str) {
+        if let Some(ty) = self.check_expr(condition)
+            && ty != TokenType::BOOL
+        {
+            self.report(
+                Diagnostic::error(
+                    condition.span(),
+                    format!("{} condition must be bool, found {}", kind, type_name(ty)),
+                )
+                .with_code(TYPE_MISMATCH)
+                .with_label("expected bool"),
+            );
+        }
+    }
+
+    // Returns None when the expression already reported an error, so one
+    // mistake doesn't cascade into a diagnostic for every enclosing expression.
+    fn check_expr(&mut self, expr: &mut Expr) -> Option<TokenType> {
+        match expr {
+            Expr::Literal { value, .. } => Some(match value {
+                LiteralValue::Int(_) => TokenType::INT,
+                LiteralValue::Number(_) => TokenType::FLOAT,
+                LiteralValue::StringValue(_) => TokenType::STRING,
+                LiteralValue::True | LiteralValue::False => TokenType::BOOL,
+                LiteralValue::Nil => TokenType::NIL,
+            }),
+            Expr::Grouping { expression, .. } => self.check_expr(expression),
+            Expr::Variable { name, .. } => {
+                let ty = self.lookup(name);
+                if ty.is_none() {
+                    self.undefined_variable(name);
+                }
+                ty
+            }
+            Expr::Assign { name, value, .. } => {
+                let value_type = self.check_expr(value);
+                let Some(ty) = self.lookup(name) else {
+                    self.undefined_variable(name);
+                    return None;
+                };
+                let value_type = self.coerce(value, value_type, ty);
+                match value_type {
+                    Some(value_type) if value_type != ty => {
+                        self.report_mismatch(
+                            Diagnostic::error(
+                                value.span(),
+                                format!(
+                                    "cannot assign a value of type {} to '{}' of type {}",
+                                    type_name(value_type),
+                                    name.lexeme,
+                                    type_name(ty)
+                                ),
+                            )
+                            .with_code(TYPE_MISMATCH)
+                            .with_label(format!("expected {}", type_name(ty))),
+                            value_type,
+                            ty,
+                        );
+                        None
+                    }
+                    Some(_) => Some(ty),
+                    None => None,
+                }
+            }
+            Expr::Unary {
+                operator, right, ..
+            } => {
+                let right_type = self.check_expr(right)?;
+                match (operator.token_type, right_type) {
+                    (TokenType::MINUS, TokenType::FLOAT) => Some(TokenType::FLOAT),
+                    (TokenType::MINUS, TokenType::INT) => Some(TokenType::INT),
+                    (TokenType::BANG, TokenType::BOOL) => Some(TokenType::BOOL),
+                    _ => {
+                        let mut diagnostic = Diagnostic::error(
+                            operator.span,
+                            format!(
+                                "cannot apply '{}' to a value of type {}",
+                                operator.lexeme,
+                                type_name(right_type)
+                            ),
+                        )
+                        .with_code(INVALID_OPERAND)
+                        .with_secondary(right.span(), type_name(right_type));
+                        // numbers have no truth value of their own
+                        if operator.token_type == TokenType::BANG
+                            && matches!(right_type, TokenType::INT | TokenType::FLOAT)
+                        {
+                            diagnostic =
+                                diagnostic.with_help("compare with zero instead, e.g. `x == 0`");
+                        }
+                        self.report(diagnostic);
+                        None
+                    }
+                }
+            }
+            Expr::Binary {
+                left,
+                operator,
+                right,
+                ..
+            } => {
+                let left_type = self.check_expr(left);
+                let right_type = self.check_expr(right);
+                let (mut left_type, mut right_type) = (left_type?, right_type?);
+                // the divisor is known, no need to wait for the run time trap
+                if matches!(operator.token_type, TokenType::SLASH | TokenType::Modulus)
+                    && (left_type, right_type) == (TokenType::INT, TokenType::INT)
+                    && is_int_constant(right)
+                    && int_constant_value(right) == Some(0)
+                {
+                    self.report(
+                        Diagnostic::error(right.span(), "division by zero")
+                            .with_code(DIVISION_BY_ZERO)
+                            .with_label("this is always zero")
+                            .with_secondary(operator.span, "divides by it"),
+                    );
+                }
+                // `x + 1` with a float x makes the 1 a float
+                if right_type == TokenType::FLOAT {
+                    left_type = self.coerce(left, Some(left_type), right_type)?;
+                }
+                if left_type == TokenType::FLOAT {
+                    right_type = self.coerce(right, Some(right_type), left_type)?;
+                }
+                let result = match (left_type, operator.token_type, right_type) {
+                    (
+                        TokenType::INT,
+                        TokenType::PLUS
+                        | TokenType::MINUS
+                        | TokenType::STAR
+                        | TokenType::SLASH
+                        | TokenType::Modulus,
+                        TokenType::INT,
+                    ) => Some(TokenType::INT),
+                    (
+                        TokenType::FLOAT,
+                        TokenType::PLUS
+                        | TokenType::MINUS
+                        | TokenType::STAR
+                        | TokenType::SLASH
+                        | TokenType::Modulus,
+                        TokenType::FLOAT,
+                    ) => Some(TokenType::FLOAT),
+                    (TokenType::STRING, TokenType::PLUS, TokenType::STRING) => {
+                        Some(TokenType::STRING)
+                    }
+                    (
+                        TokenType::INT,
+                        TokenType::GREATER
+                        | TokenType::GREATER_EQUAL
+                        | TokenType::LESS
+                        | TokenType::LESS_EQUAL
+                        | TokenType::EQUAL_EQUAL
+                        | TokenType::BANG_EQUAL,
+                        TokenType::INT,
+                    )
+                    | (
+                        TokenType::FLOAT,
+                        TokenType::GREATER
+                        | TokenType::GREATER_EQUAL
+                        | TokenType::LESS
+                        | TokenType::LESS_EQUAL
+                        | TokenType::EQUAL_EQUAL
+                        | TokenType::BANG_EQUAL,
+                        TokenType::FLOAT,
+                    )
+                    | (
+                        TokenType::STRING,
+                        TokenType::GREATER
+                        | TokenType::GREATER_EQUAL
+                        | TokenType::LESS
+                        | TokenType::LESS_EQUAL
+                        | TokenType::EQUAL_EQUAL
+                        | TokenType::BANG_EQUAL,
+                        TokenType::STRING,
+                    )
+                    | (
+                        TokenType::BOOL,
+                        TokenType::EQUAL_EQUAL | TokenType::BANG_EQUAL,
+                        TokenType::BOOL,
+                    ) => Some(TokenType::BOOL),
+                    _ => None,
+                };
+                if result.is_none() {
+                    let mut diagnostic = Diagnostic::error(
+                        operator.span,
+                        format!(
+                            "cannot apply '{}' to {} and {}",
+                            operator.lexeme,
+                            type_name(left_type),
+                            type_name(right_type)
+                        ),
+                    )
+                    .with_code(INVALID_OPERAND)
+                    .with_secondary(left.span(), type_name(left_type))
+                    .with_secondary(right.span(), type_name(right_type));
+                    if matches!(
+                        (left_type, right_type),
+                        (TokenType::INT, TokenType::FLOAT) | (TokenType::FLOAT, TokenType::INT)
+                    ) {
+                        diagnostic = diagnostic
+                            .with_note("only int constants are converted to float implicitly")
+                            .with_help("convert one side with `as float`, or `as int` to truncate");
+                    } else if operator.token_type == TokenType::PLUS
+                        && (left_type == TokenType::STRING) != (right_type == TokenType::STRING)
+                        && left_type != TokenType::NIL
+                        && right_type != TokenType::NIL
+                    {
+                        diagnostic =
+                            diagnostic.with_help("convert the other side with `as string`");
+                    }
+                    self.report(diagnostic);
+                }
+                result
+            }
+            Expr::Logical {
+                expression,
+                operator,
+                right,
+                ..
+            } => {
+                let left_type = self.check_expr(expression);
+                let right_type = self.check_expr(right);
+                // report both operands before giving up on the expression
+                let mut valid = true;
+                for (operand, ty) in [(expression, left_type), (right, right_type)] {
+                    if let Some(ty) = ty
+                        && ty != TokenType::BOOL
+                    {
+                        valid = false;
+                        self.report(
+                            Diagnostic::error(
+                                operand.span(),
+                                format!(
+                                    "operands of '{}' must be bool, found {}",
+                                    operator.lexeme,
+                                    type_name(ty)
+                                ),
+                            )
+                            .with_code(TYPE_MISMATCH)
+                            .with_label("expected bool"),
+                        );
+                    }
+                }
+                if !valid {
+                    return None;
+                }
+                left_type.and(right_type)
+            }
+            Expr::Interpolated { parts, .. } => {
+                for part in parts {
+                    if let Some(TokenType::NIL) = self.check_expr(part) {
+                        self.report(
+                            Diagnostic::error(
+                                part.span(),
+                                "cannot interpolate a value of type nil",
+                            )
+                            .with_code(TYPE_MISMATCH)
+                            .with_label("this has no value"),
+                        );
+                    }
+                }
+                Some(TokenType::STRING)
+            }
+            Expr::Cast {
+                expression,
+                keyword,
+                target,
+                ..
+            } => {
+                let ty = self.check_expr(expression)?;
+                match (ty, target.token_type) {
+                    (from, to) if from == to => Some(to),
+                    (TokenType::INT, TokenType::FLOAT)
+                    | (TokenType::FLOAT, TokenType::INT)
+                    | (TokenType::BOOL, TokenType::INT)
+                    | (TokenType::INT | TokenType::FLOAT | TokenType::BOOL, TokenType::STRING) => {
+                        Some(target.token_type)
+                    }
+                    (from, to) => {
+                        let mut diagnostic = Diagnostic::error(
+                            keyword.span.to(target.span),
+                            format!("cannot cast {} to {}", type_name(from), type_name(to)),
+                        )
+                        .with_code(INVALID_CAST)
+                        .with_secondary(expression.span(), type_name(from));
+                        if to == TokenType::BOOL
+                            && matches!(from, TokenType::INT | TokenType::FLOAT)
+                        {
+                            diagnostic =
+                                diagnostic.with_help("compare with zero instead, e.g. `x != 0`");
+                        }
+                        self.report(diagnostic);
+                        None
+                    }
+                }
+            }
+            Expr::Call {
+                callie, args, span, ..
+            } => {
+                let arg_types: Vec<Option<TokenType>> =
+                    args.iter_mut().map(|arg| self.check_expr(arg)).collect();
+
+                let Expr::Variable { name, .. } = callie.as_ref() else {
+                    self.report(
+                        Diagnostic::error(callie.span(), "can only call functions")
+                            .with_code(INVALID_OPERAND),
+                    );
+                    return None;
+                };
+                // declared is None for built-in functions
+                let (param_types, return_type, declared) = match self.functions.get(&name.lexeme) {
+                    Some((params, return_type, span)) => {
+                        (params.clone(), *return_type, Some(*span))
+                    }
+                    None => match Builtin::lookup(&name.lexeme) {
+                        Some(builtin) => (builtin.params().to_vec(), builtin.return_type(), None),
+                        None => {
+                            self.report(
+                                Diagnostic::error(
+                                    name.span,
+                                    format!("undefined function '{}'", name.lexeme),
+                                )
+                                .with_code(UNDEFINED_NAME)
+                                .with_label("not found in this scope"),
+                            );
+                            return None;
+                        }
+                    },
+                };
+
+                if param_types.len() != arg_types.len() {
+                    self.report(declared_at(
+                        Diagnostic::error(
+                            *span,
+                            format!(
+                                "'{}' expects {} arguments but got {}",
+                                name.lexeme,
+                                param_types.len(),
+                                arg_types.len()
+                            ),
+                        )
+                        .with_code(ARITY_MISMATCH),
+                        name,
+                        declared,
+                    ));
+                    return Some(return_type);
+                }
+                for (i, ((param, arg), arg_expr)) in
+                    param_types.iter().zip(arg_types).zip(args.iter_mut()).enumerate()
+                {
+                    if let Some(arg) = self.coerce(arg_expr, arg, *param)
+                        && arg != *param
+                    {
+                        self.report_mismatch(
+                            declared_at(
+                                Diagnostic::error(
+                                    arg_expr.span(),
+                                    format!(
+                                        "argument {} of '{}' expects {} but found {}",
+                                        i + 1,
+                                        name.lexeme,
+                                        type_name(*param),
+                                        type_name(arg)
+                                    ),
+                                )
+                                .with_code(TYPE_MISMATCH)
+                                .with_label(format!("expected {}", type_name(*param))),
+                                name,
+                                declared,
+                            ),
+                            arg,
+                            *param,
+                        );
+                    }
+                }
+                Some(return_type)
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &Token, ty: TokenType) {
+        // redeclaring in the same scope is allowed and simply replaces the old variable
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.lexeme.clone(), ty);
+    }
+
+    fn declare_function(
+        &mut self,
+        name: &Token,
+        params: &[(Token, TokenType)],
+        return_type: TokenType,
+    ) {
+        self.functions.insert(
+            name.lexeme.clone(),
+            (
+                params.iter().map(|(_, ty)| *ty).collect(),
+                return_type,
+                name.span,
+            ),
+        );
+    }
+
+    fn lookup(&self, name: &Token) -> Option<TokenType> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name.lexeme).copied())
+    }
+
+    fn undefined_variable(&mut self, name: &Token) {
+        let mut diagnostic =
+            Diagnostic::error(name.span, format!("undefined variable '{}'", name.lexeme))
+                .with_code(UNDEFINED_NAME)
+                .with_label("not found in this scope");
+        if self.current_function.is_some() {
+            diagnostic =
+                diagnostic.with_note("functions can only see their own parameters and locals");
+        }
+        self.report(diagnostic);
+    }
+
+    fn report(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    // Integer constants take the float type where a float is expected, so
+    // `float x = 1;` and `x * 2` keep working. `expr` of type `ty` is promoted
+    // in place when it is a constant made of int literals whose value floats
+    // represent exactly, and the type it ends up with is returned. Anything
+    // else, int variables included, needs an explicit `as float`.
+    //
+    // The constant is still evaluated as an int and only its value is
+    // converted, so `float m = 7 / 2;` is 3 just like it would be in an int
+    // context. Divisions that drop a remainder get a warning.
+    fn coerce(
+        &mut self,
+        expr: &mut Expr,
+        ty: Option<TokenType>,
+        expected: TokenType,
+    ) -> Option<TokenType> {
+        if ty != Some(TokenType::INT) || expected != TokenType::FLOAT || !is_int_constant(expr) {
+            return ty;
+        }
+        // beyond 2^53 not every int has a float of the same value, a division by
+        // zero has no value but was already reported
+        if let Some(n) = int_constant_value(expr)
+            && n.unsigned_abs() > 1 << 53
+        {
+            return ty;
+        }
+        let mut truncated = vec![];
+        truncating_divisions(expr, &mut truncated);
+        for (operator, division) in truncated {
+            self.report(
+                Diagnostic::warning(operator, "integer division truncates in a float constant")
+                    .with_label(division)
+                    .with_note("int constants are computed as ints, then converted to float")
+                    .with_help("write one operand as a float, e.g. `2.0`, to keep the fraction"),
+            );
+        }
+        int_constant_to_float(expr);
+        Some(TokenType::FLOAT)
+    }
+
+    // reports a value of type `found` where `expected` was needed, pointing
+    // at `as` when an explicit conversion exists
+    fn report_mismatch(
+        &mut self,
+        mut diagnostic: Diagnostic,
+        found: TokenType,
+        expected: TokenType,
+    ) {
+        if matches!(
+            (found, expected),
+            (TokenType::INT, TokenType::FLOAT)
+                | (TokenType::FLOAT, TokenType::INT)
+                | (TokenType::BOOL, TokenType::INT)
+                | (
+                    TokenType::INT | TokenType::FLOAT | TokenType::BOOL,
+                    TokenType::STRING
+                )
+        ) {
+            diagnostic = diagnostic.with_help(format!(
+                "convert it explicitly with `as {}`",
+                type_name(expected)
+            ));
+        }
+        self.report(diagnostic);
+    }
+}
+
+// points at the declaration of the function `name`, built-ins have none
+fn declared_at(diagnostic: Diagnostic, name: &Token, declared: Option<Span>) -> Diagnostic {
+    match declared {
+        Some(span) => diagnostic.with_secondary(span, "function declared here"),
+        None => diagnostic.with_note(format!("'{}' is a built-in function", name.lexeme)),
+    }
+}
+
+fn type_name(ty: TokenType) -> &'static str {
+    match ty {
+        TokenType::INT => "int",
+        TokenType::FLOAT => "float",
+        TokenType::BOOL => "bool",
+        TokenType::STRING => "string",
+        TokenType::NIL => "nil",
+        _ => "unknown",
+    }
+}
+
+fn is_int_constant(expr: &Expr) -> bool {
+    match expr {
+        Expr::Literal {
+            value: LiteralValue::Int(_),
+            ..
+        } => true,
+        Expr::Grouping { expression, .. } => is_int_constant(expression),
+        Expr::Unary {
+            operator, right, ..
+        } => operator.token_type == TokenType::MINUS && is_int_constant(right),
+        Expr::Binary {
+            left,
+            operator,
+            right,
+            ..
+        } => {
+            matches!(
+                operator.token_type,
+                TokenType::PLUS
+                    | TokenType::MINUS
+                    | TokenType::STAR
+                    | TokenType::SLASH
+                    | TokenType::Modulus
+            ) && is_int_constant(left)
+                && is_int_constant(right)
+        }
+        _ => false,
+    }
+}
+
+// folds an int constant the way the program would compute it, None when it
+// divides by zero
+fn int_constant_value(expr: &Expr) -> Option<i64> {
+    match expr {
+        Expr::Literal {
+            value: LiteralValue::Int(n),
+            ..
+        } => Some(*n),
+        Expr::Grouping { expression, .. } => int_constant_value(expression),
+        Expr::Unary { right, .. } => Some(int_constant_value(right)?.wrapping_neg()),
+        Expr::Binary {
+            left,
+            operator,
+            right,
+            ..
+        } => {
+            let (a, b) = (int_constant_value(left)?, int_constant_value(right)?);
+            match operator.token_type {
+                TokenType::PLUS => Some(a.wrapping_add(b)),
+                TokenType::MINUS => Some(a.wrapping_sub(b)),
+                TokenType::STAR => Some(a.wrapping_mul(b)),
+                TokenType::SLASH if b != 0 => Some(a.wrapping_div(b)),
+                TokenType::Modulus if b != 0 => Some(a.wrapping_rem(b)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+// collects the `/` operators in an int constant that drop a remainder, with
+// a label showing what they compute
+fn truncating_divisions(expr: &Expr, found: &mut Vec<(Span, String)>) {
+    match expr {
+        Expr::Grouping { expression, .. } => truncating_divisions(expression, found),
+        Expr::Unary { right, .. } => truncating_divisions(right, found),
+        Expr::Binary {
+            left,
+            operator,
+            right,
+            ..
+        } => {
+            truncating_divisions(left, found);
+            truncating_divisions(right, found);
+            if operator.token_type == TokenType::SLASH
+                && let (Some(a), Some(b)) = (int_constant_value(left), int_constant_value(right))
+                && b != 0
+                && a.wrapping_rem(b) != 0
+            {
+                found.push((
+                    operator.span,
+                    format!("{} / {} is {}", a, b, a.wrapping_div(b)),
+                ));
+            }
+        }
+        _ => (),
+    }
+}
+
+// A lone literal becomes a float literal, anything bigger is wrapped in an
+// `as float` so its operators keep their int meaning.
+fn int_constant_to_float(expr: &mut Expr) {
+    match expr {
+        Expr::Literal { value, .. } => {
+            if let LiteralValue::Int(n) = value {
+                *value = LiteralValue::Number(*n as f64);
+            }
+        }
+        _ => {
+            let span = expr.span();
+            let placeholder = Expr::Literal {
+                value: LiteralValue::Nil,
+                span,
+            };
+            let expression = std::mem::replace(expr, placeholder);
+            *expr = Expr::Cast {
+                expression: Box::new(expression),
+                keyword: Token::new(TokenType::AS, "as".to_owned(), None, span),
+                target: Token::new(TokenType::FLOAT, "float".to_owned(), None, span),
+                span,
+            };
+        }
+    }
+}
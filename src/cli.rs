@@ -0,0 +1,239 @@
+use std::{fmt::Display, path::PathBuf};
+
+#[cfg(feature = "llvm")]
+use inkwell::OptimizationLevel;
+
+pub const USAGE: &str = "\
+Usage: testlang <command> [options] <file>
+       testlang            start an interactive session
+
+Commands:
+    build    compile <file> to an executable (or --emit kind)
+    run      compile <file> and run it
+    check    report errors in <file> without generating code
+    emit     print the compiled <file> (LLVM IR unless --emit is given)
+
+Options:
+    -o <path>          write the output to <path>
+    --emit=<kind>      obj, asm, llvm-ir, bitcode or exe
+    --backend=<name>   run with llvm (the default) or interp, the AST interpreter
+    -O0, -O1, -O2, -O3 optimization level (default -O2)
+    -h, --help         print this message";
+
+// Exit codes, following the BSD sysexits convention.
+pub const EXIT_USAGE: i32 = 64;
+pub const EXIT_COMPILE_ERROR: i32 = 65;
+pub const EXIT_NO_INPUT: i32 = 66;
+pub const EXIT_UNAVAILABLE: i32 = 69;
+pub const EXIT_INTERNAL: i32 = 70;
+#[cfg(feature = "llvm")]
+pub const EXIT_IO_ERROR: i32 = 74;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    Build,
+    Run,
+    Check,
+    Emit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Emit {
+    Object,
+    Assembly,
+    LlvmIr,
+    Bitcode,
+    Executable,
+}
+
+impl Emit {
+    fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "obj" => Some(Emit::Object),
+            "asm" => Some(Emit::Assembly),
+            "llvm-ir" => Some(Emit::LlvmIr),
+            "bitcode" => Some(Emit::Bitcode),
+            "exe" => Some(Emit::Executable),
+            _ => None,
+        }
+    }
+
+    // extension of the file `build` writes when no `-o` is given
+    #[cfg(feature = "llvm")]
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Emit::Object => "o",
+            Emit::Assembly => "s",
+            Emit::LlvmIr => "ll",
+            Emit::Bitcode => "bc",
+            Emit::Executable => "",
+        }
+    }
+}
+
+// the -O level, kept apart from inkwell's so builds without LLVM parse it too
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OptLevel {
+    None,
+    Less,
+    Default,
+    Aggressive,
+}
+
+#[cfg(feature = "llvm")]
+impl From<OptLevel> for OptimizationLevel {
+    fn from(level: OptLevel) -> Self {
+        match level {
+            OptLevel::None => OptimizationLevel::None,
+            OptLevel::Less => OptimizationLevel::Less,
+            OptLevel::Default => OptimizationLevel::Default,
+            OptLevel::Aggressive => OptimizationLevel::Aggressive,
+        }
+    }
+}
+
+// what `run` executes the program with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backend {
+    Llvm,
+    Interp,
+}
+
+#[cfg(feature = "llvm")]
+const DEFAULT_BACKEND: Backend = Backend::Llvm;
+#[cfg(not(feature = "llvm"))]
+const DEFAULT_BACKEND: Backend = Backend::Interp;
+
+// Without LLVM only `check` and the interpreter run, which write no files
+// and optimize nothing, so those options are still parsed but not kept.
+#[derive(Debug)]
+pub struct Options {
+    pub command: Command,
+    pub input: PathBuf,
+    #[cfg(feature = "llvm")]
+    pub output: Option<PathBuf>,
+    #[cfg(feature = "llvm")]
+    pub emit: Emit,
+    #[cfg(feature = "llvm")]
+    pub opt_level: OptLevel,
+    pub backend: Backend,
+}
+
+#[cfg(feature = "llvm")]
+impl Options {
+    // where `build` writes its output: `-o` if given, else the input with the emit extension
+    pub fn output_path(&self) -> PathBuf {
+        match &self.output {
+            Some(path) => path.clone(),
+            // don't let an executable overwrite an input that has no extension
+            None if self.input.extension().is_none() && self.emit == Emit::Executable => {
+                self.input.with_extension("out")
+            }
+            None => self.input.with_extension(self.emit.extension()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CliError {
+    Help,
+    Usage(String),
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Help => write!(f, "{}", USAGE),
+            CliError::Usage(msg) => write!(f, "error: {}\n\n{}", msg, USAGE),
+        }
+    }
+}
+
+/// Parses the arguments following the program name.
+pub fn parse_args(args: &[String]) -> Result<Options, CliError> {
+    let mut args = args.iter();
+    let command = match args.next().map(String::as_str) {
+        Some("build") => Command::Build,
+        Some("run") => Command::Run,
+        Some("check") => Command::Check,
+        Some("emit") => Command::Emit,
+        Some("-h" | "--help") => return Err(CliError::Help),
+        Some(other) => return Err(usage(format!("unknown command '{}'", other))),
+        None => return Err(usage("missing command")),
+    };
+
+    let mut input = None;
+    let mut output = None;
+    let mut emit = None;
+    let mut opt_level = OptLevel::Default;
+    let mut backend = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Err(CliError::Help),
+            "-o" => match args.next() {
+                Some(path) => output = Some(PathBuf::from(path)),
+                None => return Err(usage("'-o' expects a path")),
+            },
+            "-O0" => opt_level = OptLevel::None,
+            "-O1" => opt_level = OptLevel::Less,
+            "-O2" => opt_level = OptLevel::Default,
+            "-O3" => opt_level = OptLevel::Aggressive,
+            arg if arg.starts_with("--emit=") => {
+                let kind = &arg["--emit=".len()..];
+                match Emit::parse(kind) {
+                    Some(kind) => emit = Some(kind),
+                    None => return Err(usage(format!("unknown emit kind '{}'", kind))),
+                }
+            }
+            arg if arg.starts_with("--backend=") => match &arg["--backend=".len()..] {
+                "llvm" => backend = Some(Backend::Llvm),
+                "interp" => backend = Some(Backend::Interp),
+                name => return Err(usage(format!("unknown backend '{}'", name))),
+            },
+            arg if arg.starts_with('-') => {
+                return Err(usage(format!("unknown option '{}'", arg)));
+            }
+            path => {
+                if input.is_some() {
+                    return Err(usage(format!("unexpected argument '{}'", path)));
+                }
+                input = Some(PathBuf::from(path));
+            }
+        }
+    }
+
+    let Some(input) = input else {
+        return Err(usage("missing input file"));
+    };
+    if emit.is_some() && !matches!(command, Command::Build | Command::Emit) {
+        return Err(usage("'--emit' only applies to build and emit"));
+    }
+    if backend.is_some() && command != Command::Run {
+        return Err(usage("'--backend' only applies to run"));
+    }
+    let emit = emit.unwrap_or(match command {
+        Command::Emit => Emit::LlvmIr,
+        _ => Emit::Executable,
+    });
+    if command == Command::Emit && emit == Emit::Executable && output.is_none() {
+        return Err(usage("'--emit=exe' needs an output path, pass '-o <path>'"));
+    }
+
+    #[cfg(not(feature = "llvm"))]
+    let _ = opt_level;
+    Ok(Options {
+        command,
+        input,
+        #[cfg(feature = "llvm")]
+        output,
+        #[cfg(feature = "llvm")]
+        emit,
+        #[cfg(feature = "llvm")]
+        opt_level,
+        backend: backend.unwrap_or(DEFAULT_BACKEND),
+    })
+}
+
+fn usage(msg: impl Into<String>) -> CliError {
+    CliError::Usage(msg.into())
+}
@@ -1,18 +1,26 @@
-use std::{collections::HashMap, env::set_var, ops::Deref, panic};
+use std::{collections::HashMap, env::set_var, panic};
 
 use inkwell::{
-    AddressSpace,
+    AddressSpace, IntPredicate,
+    basic_block::BasicBlock,
     builder::Builder,
     context::{self, Context},
-    module::Module,
-    types::BasicTypeEnum,
-    values::{BasicValueEnum, FunctionValue, PointerValue},
+    intrinsics::Intrinsic,
+    module::{Linkage, Module},
+    types::{BasicMetadataTypeEnum, BasicType, BasicTypeEnum},
+    values::{
+        BasicMetadataValueEnum, BasicValueEnum, FunctionValue, IntValue, PointerValue, StructValue,
+    },
 };
 
 use crate::{
+    builtin::Builtin,
+    cli::EXIT_INTERNAL,
+    diagnostic::{CODEGEN_ERROR, Diagnostic},
     expr::{self, Expr, LiteralValue},
+    runtime,
     stmt::Stmt,
-    token::Literal,
+    token::{Literal, Span, Token},
     tokentype::TokenType,
 };
 
@@ -22,8 +30,13 @@ pub struct Compiler<'ctx> {
     pub module: Module<'ctx>,
 
     variables: HashMap<String, (TokenType, BasicTypeEnum<'ctx>, PointerValue<'ctx>)>,
+    functions: HashMap<String, (FunctionValue<'ctx>, Vec<TokenType>, TokenType)>,
+    // (continue target, break target) of every loop we are currently inside
+    loops: Vec<(BasicBlock<'ctx>, BasicBlock<'ctx>)>,
 
     print_f: FunctionValue<'ctx>,
+    // number of the REPL entry being compiled, 0 outside the REPL
+    repl_entry: usize,
 }
 
 impl<'ctx> Compiler<'ctx> {
@@ -41,10 +54,13 @@ impl<'ctx> Compiler<'ctx> {
             builder,
             module,
             variables: HashMap::new(),
+            functions: HashMap::new(),
+            loops: vec![],
             print_f,
+            repl_entry: 0,
         }
     }
-    pub fn generate(&mut self, st: Vec<Stmt>) {
+    pub fn generate(&mut self, st: Vec<Stmt>) -> Result<(), Diagnostic> {
         let i32_type = self.context.i32_type();
         let fn_type = i32_type.fn_type(&[], false);
         let function = self.module.add_function("main", fn_type, None);
@@ -52,42 +68,174 @@ impl<'ctx> Compiler<'ctx> {
 
         self.builder.position_at_end(basic_block);
 
+        // declare every top level function up front so calls can appear
+        // before the definition and functions can call each other
+        for statement in &st {
+            if let Stmt::Function {
+                name,
+                params,
+                return_type,
+                ..
+            } = statement
+            {
+                self.declare_function(name, params, *return_type);
+            }
+        }
+
         for statement in st {
-            self.compile_statement(statement, function);
+            self.compile_statement(statement, function)?;
         }
 
         // returning 0
         let _ = self
             .builder
             .build_return(Some(&i32_type.const_int(0, false)));
+        Ok(())
+    }
+
+    /// Compiles one REPL entry into a fresh module, as a `void` function that
+    /// runs its top level statements, and returns that function's name.
+    ///
+    /// Top level variables become globals and every symbol gets the entry
+    /// number as a suffix, so later entries, each in a module of its own, can
+    /// keep using them or shadow them with new definitions. Bare expression
+    /// statements print their value.
+    pub fn generate_entry(&mut self, st: Vec<Stmt>) -> Result<String, Diagnostic> {
+        let outer_variables = self.variables.clone();
+        let outer_functions = self.functions.clone();
+        self.repl_entry += 1;
+        self.start_module();
+        let result = self.compile_entry(st);
+        if result.is_err() {
+            // the module is thrown away, forget the names it would have defined
+            self.variables = outer_variables;
+            self.functions = outer_functions;
+        }
+        result
+    }
+
+    fn compile_entry(&mut self, st: Vec<Stmt>) -> Result<String, Diagnostic> {
+        let name = format!("entry.{}", self.repl_entry);
+        let fn_type = self.context.void_type().fn_type(&[], false);
+        let function = self.module.add_function(&name, fn_type, None);
+        let basic_block = self.context.append_basic_block(function, "entry");
+        self.builder.position_at_end(basic_block);
+
+        for statement in &st {
+            if let Stmt::Function {
+                name,
+                params,
+                return_type,
+                ..
+            } = statement
+            {
+                // a redefinition gets a new symbol instead of the previous entry's declaration
+                self.functions.remove(&name.lexeme);
+                self.declare_function(name, params, *return_type);
+            }
+        }
+
+        for statement in st {
+            match statement {
+                Stmt::Var {
+                    name,
+                    data_type,
+                    initializer,
+                    ..
+                } => {
+                    let var_type = self.get_basic_type(data_type);
+                    let value = match initializer {
+                        Expr::Literal {
+                            value: LiteralValue::Nil,
+                            ..
+                        } => self.zero_value(data_type),
+                        initializer => self.compile_expr(initializer)?.1,
+                    };
+                    let symbol = format!("{}.{}", name.lexeme, self.repl_entry);
+                    let global = self.module.add_global(var_type, None, &symbol);
+                    global.set_initializer(&var_type.const_zero());
+                    self.builder
+                        .build_store(global.as_pointer_value(), value)
+                        .unwrap();
+                    self.variables.insert(
+                        name.lexeme.clone(),
+                        (data_type, var_type, global.as_pointer_value()),
+                    );
+                }
+                // assignments are statements here, only echo what the user asked to see
+                Stmt::Expression { expression, .. }
+                    if !matches!(expression, Expr::Assign { .. }) =>
+                {
+                    let value = self.compile_expr(expression)?;
+                    if value.0 != TokenType::NIL {
+                        self.build_print_call(value.1, value.0);
+                    }
+                }
+                statement => self.compile_statement(statement, function)?,
+            }
+        }
+
+        self.builder.build_return(None).unwrap();
+        Ok(name)
+    }
+
+    // Replaces `module` with an empty one that declares everything the
+    // previous entries defined, so the JIT can link the new code against them.
+    fn start_module(&mut self) {
+        let module = self
+            .context
+            .create_module(&format!("entry.{}", self.repl_entry));
+        self.print_f = module.add_function("printf", self.print_f.get_type(), None);
+        for (_, ty, pointer) in self.variables.values_mut() {
+            let symbol = pointer.get_name().to_str().unwrap().to_string();
+            let global = module.add_global(*ty, None, &symbol);
+            global.set_linkage(Linkage::External);
+            *pointer = global.as_pointer_value();
+        }
+        for (function, _, _) in self.functions.values_mut() {
+            let symbol = function.get_name().to_str().unwrap().to_string();
+            *function = module.add_function(&symbol, function.get_type(), Some(Linkage::External));
+        }
+        self.module = module;
     }
 
-    pub fn compile_statement(&mut self, st: Stmt, func: FunctionValue<'ctx>) {
+    pub fn compile_statement(
+        &mut self,
+        st: Stmt,
+        func: FunctionValue<'ctx>,
+    ) -> Result<(), Diagnostic> {
         match st {
             Stmt::Var {
                 name,
                 data_type,
                 initializer,
+                ..
             } => {
                 let var_type = self.get_basic_type(data_type);
-                let alloca = self.builder.build_alloca(var_type, &name.lexeme).unwrap();
+                let alloca = self.create_entry_block_alloca(func, var_type, &name.lexeme);
+
+                // a declaration without initializer holds the zero value of its type
+                let expr_value = match initializer {
+                    Expr::Literal {
+                        value: LiteralValue::Nil,
+                        ..
+                    } => self.zero_value(data_type),
+                    initializer => self.compile_expr(initializer)?.1,
+                };
+                self.builder.build_store(alloca, expr_value).unwrap();
 
                 self.variables
                     .insert(name.lexeme.clone(), (data_type, var_type, alloca));
-
-                let expr_value = self.compile_expr(initializer);
-                self.builder.build_store(alloca, expr_value.1).unwrap();
             }
 
             Stmt::IfElse {
                 condition,
                 then,
                 els,
+                ..
             } => {
-                let condition = self.compile_expr(condition);
-                if let Some(else_block) = els
-                    && TokenType::INT == condition.0
-                {
+                let condition = self.compile_expr(condition)?;
+                if let Some(else_block) = els {
                     let then_basic_block = self.context.append_basic_block(func, "if_block");
                     let else_basic_block = self.context.append_basic_block(func, "else_block");
                     let merge_basic_block = self.context.append_basic_block(func, "merge_block");
@@ -101,24 +249,14 @@ impl<'ctx> Compiler<'ctx> {
                         .unwrap();
 
                     self.builder.position_at_end(then_basic_block);
-                    let Stmt::Block { stmts } = *then else {
-                        panic!("Expected block");
-                    };
-                    for i in stmts {
-                        self.compile_statement(i, func);
-                    }
+                    self.compile_statement(*then, func)?;
 
                     self.builder
                         .build_unconditional_branch(merge_basic_block)
                         .unwrap();
                     self.builder.position_at_end(else_basic_block);
 
-                    let Stmt::Block { stmts } = else_block.deref() else {
-                        panic!("Expected block");
-                    };
-                    for i in stmts {
-                        self.compile_statement(i.clone(), func);
-                    }
+                    self.compile_statement(*else_block, func)?;
                     self.builder
                         .build_unconditional_branch(merge_basic_block)
                         .unwrap();
@@ -139,12 +277,7 @@ impl<'ctx> Compiler<'ctx> {
                             .unwrap();
 
                         self.builder.position_at_end(then_basic_block);
-                        let Stmt::Block { stmts } = *then else {
-                            panic!("Expected block");
-                        };
-                        for i in stmts {
-                            self.compile_statement(i, func);
-                        }
+                        self.compile_statement(*then, func)?;
 
                         self.builder
                             .build_unconditional_branch(merge_basic_block)
@@ -153,139 +286,587 @@ impl<'ctx> Compiler<'ctx> {
                     }
                 }
             }
-            Stmt::WHILE { condition, block } => {
+            Stmt::WHILE {
+                condition,
+                block,
+                increment,
+                ..
+            } => {
                 let before_while = self.context.append_basic_block(func, "before_while");
                 let then_while = self.context.append_basic_block(func, "then_while");
+                let increment_while = increment
+                    .as_ref()
+                    .map(|_| self.context.append_basic_block(func, "increment_while"));
                 let merge_basic_block = self.context.append_basic_block(func, "merge_block_while");
 
-                self.builder.build_unconditional_branch(before_while).unwrap();
+                self.builder
+                    .build_unconditional_branch(before_while)
+                    .unwrap();
                 self.builder.position_at_end(before_while);
 
-                let condition = self.compile_expr(condition);
-
-                if condition.0 == TokenType::INT{
-                    self.builder.build_conditional_branch(condition.1.into_int_value(),
+                let condition_span = condition.span();
+                let condition = self.compile_expr(condition)?;
+                if !condition.1.is_int_value() {
+                    return Err(self.error(condition_span, "while condition must be a bool"));
+                }
+                self.builder
+                    .build_conditional_branch(
+                        condition.1.into_int_value(),
                         then_while,
-                        merge_basic_block).unwrap();
-                    self.builder.position_at_end(then_while);
-                    let Stmt::Block { stmts } = *block else {
-                            panic!("Expected block");
-                    };
-                    for i in stmts {
-                        self.compile_statement(i, func);
-                    }
+                        merge_basic_block,
+                    )
+                    .unwrap();
+
+                // `continue` has to go through the increment of a desugared `for`
+                let continue_target = increment_while.unwrap_or(before_while);
+                self.loops.push((continue_target, merge_basic_block));
+                self.builder.position_at_end(then_while);
+                self.compile_statement(*block, func)?;
+                self.loops.pop();
+
+                self.builder
+                    .build_unconditional_branch(continue_target)
+                    .unwrap();
+                if let (Some(increment_while), Some(increment)) = (increment_while, increment) {
+                    self.builder.position_at_end(increment_while);
+                    self.compile_expr(increment)?;
+                    self.builder
+                        .build_unconditional_branch(before_while)
+                        .unwrap();
+                }
 
-                self.builder.build_unconditional_branch(before_while).unwrap();
-                    
                 self.builder.position_at_end(merge_basic_block);
-                }else {
-                    panic!("Error");
+            }
+            Stmt::Break { token, .. } => {
+                let Some((_, break_target)) = self.loops.last().copied() else {
+                    return Err(self.error(token.span, "'break' outside of a loop"));
+                };
+                self.builder
+                    .build_unconditional_branch(break_target)
+                    .unwrap();
+                let after_break = self.context.append_basic_block(func, "after_break");
+                self.builder.position_at_end(after_break);
+            }
+            Stmt::Continue { token, .. } => {
+                let Some((continue_target, _)) = self.loops.last().copied() else {
+                    return Err(self.error(token.span, "'continue' outside of a loop"));
+                };
+                self.builder
+                    .build_unconditional_branch(continue_target)
+                    .unwrap();
+                let after_continue = self.context.append_basic_block(func, "after_continue");
+                self.builder.position_at_end(after_continue);
+            }
+            Stmt::Block { stmts, .. } => {
+                // names declared inside the block go out of scope with it
+                let outer_variables = self.variables.clone();
+                for statement in stmts {
+                    self.compile_statement(statement, func)?;
                 }
-            },
-            Stmt::Expression { expression }=>{
-                self.compile_expr(expression);
+                self.variables = outer_variables;
             }
-            Stmt::Print { expression } => {
-                let expr = self.compile_expr(expression);
+            Stmt::Expression { expression, .. } => {
+                self.compile_expr(expression)?;
+            }
+            Stmt::Print { expression, .. } => {
+                let expr = self.compile_expr(expression)?;
                 self.build_print_call(expr.1, expr.0);
             }
-            _ => panic!("uknown values"),
+            Stmt::Function {
+                name,
+                params,
+                return_type,
+                body,
+                ..
+            } => {
+                self.compile_function(name, params, return_type, body)?;
+            }
+            Stmt::Return { token, expr, .. } => {
+                let expected = func.get_type().get_return_type();
+                match (expr, expected) {
+                    (Some(expr), Some(expected)) => {
+                        let value = self.compile_expr(expr)?;
+                        if value.1.get_type() != expected {
+                            return Err(self.error(
+                                token.span,
+                                format!(
+                                    "mismatched return type {:?} in {}",
+                                    value.0,
+                                    func.get_name().to_str().unwrap()
+                                ),
+                            ));
+                        }
+                        self.builder.build_return(Some(&value.1)).unwrap();
+                    }
+                    (None, None) => {
+                        self.builder.build_return(None).unwrap();
+                    }
+                    (Some(_), None) => {
+                        return Err(self.error(
+                            token.span,
+                            format!(
+                                "{} does not return a value",
+                                func.get_name().to_str().unwrap()
+                            ),
+                        ));
+                    }
+                    (None, Some(_)) => {
+                        return Err(self.error(
+                            token.span,
+                            format!("{} must return a value", func.get_name().to_str().unwrap()),
+                        ));
+                    }
+                }
+
+                // anything after a return is dead, but still needs a block to live in
+                let after_return = self.context.append_basic_block(func, "after_return");
+                self.builder.position_at_end(after_return);
+            }
         }
+        Ok(())
     }
 
-    pub fn build_print_call(&mut self, value: BasicValueEnum<'ctx>, typ: TokenType) {
-        let mut value = value;
-        let format_str = match value.clone() {
-            BasicValueEnum::FloatValue(_) => self.builder.build_global_string_ptr("%f\n", "fmt"),
-            BasicValueEnum::PointerValue(_) => {
-                self.builder.build_global_string_ptr("%s\n", "fmt_str")
-            }
-            BasicValueEnum::IntValue(a) => {
-                if typ == TokenType::BOOL {
-                    let true_str = self
-                        .builder
-                        .build_global_string_ptr("true", "true_str")
-                        .unwrap()
-                        .as_pointer_value();
-                    let false_str = self
-                        .builder
-                        .build_global_string_ptr("false", "false_str")
-                        .unwrap()
-                        .as_pointer_value();
+    fn declare_function(
+        &mut self,
+        name: &Token,
+        params: &[(Token, TokenType)],
+        return_type: TokenType,
+    ) -> FunctionValue<'ctx> {
+        if let Some(function) = self.functions.get(&name.lexeme) {
+            return function.0;
+        }
+        let param_types: Vec<BasicMetadataTypeEnum<'ctx>> = params
+            .iter()
+            .map(|(_, ty)| self.get_basic_type(*ty).into())
+            .collect();
+        // NIL stands in for "no return value"
+        let fn_type = match return_type {
+            TokenType::NIL => self.context.void_type().fn_type(&param_types, false),
+            ty => self.get_basic_type(ty).fn_type(&param_types, false),
+        };
+        // the prefix keeps user functions from taking over `main`, libc or runtime symbols
+        let symbol = match self.repl_entry {
+            0 => format!("tl.{}", name.lexeme),
+            entry => format!("tl.{}.{}", name.lexeme, entry),
+        };
+        let function = self.module.add_function(&symbol, fn_type, None);
+        self.functions.insert(
+            name.lexeme.clone(),
+            (
+                function,
+                params.iter().map(|(_, ty)| *ty).collect(),
+                return_type,
+            ),
+        );
+        function
+    }
 
-                    value = self
-                        .builder
-                        .build_select(a, true_str, false_str, "bool_str")
-                        .unwrap();
+    fn compile_function(
+        &mut self,
+        name: Token,
+        params: Vec<(Token, TokenType)>,
+        return_type: TokenType,
+        body: Vec<Stmt>,
+    ) -> Result<(), Diagnostic> {
+        let function = self.declare_function(&name, &params, return_type);
+        let entry = self.context.append_basic_block(function, "entry");
 
-                    self.builder.build_global_string_ptr("%s\n", "fmt_bool")
-                } else {
-                    self.builder.build_global_string_ptr("%d\n", "fmt")
-                }
-            }
-            _ => panic!("error 5"),
+        // functions get a fresh set of variables, restored once the body is done
+        let outer_block = self.builder.get_insert_block();
+        let outer_variables = std::mem::take(&mut self.variables);
+        let outer_loops = std::mem::take(&mut self.loops);
+        self.builder.position_at_end(entry);
+
+        for (i, (param, param_type)) in params.iter().enumerate() {
+            let value = function.get_nth_param(i as u32).unwrap();
+            value.set_name(&param.lexeme);
+            let ty = self.get_basic_type(*param_type);
+            let alloca = self.create_entry_block_alloca(function, ty, &param.lexeme);
+            self.builder.build_store(alloca, value).unwrap();
+            self.variables
+                .insert(param.lexeme.clone(), (*param_type, ty, alloca));
+        }
+
+        for statement in body {
+            self.compile_statement(statement, function)?;
+        }
+
+        // falling off the end of a function returns the zero value of its type
+        if self
+            .builder
+            .get_insert_block()
+            .and_then(|block| block.get_terminator())
+            .is_none()
+        {
+            match return_type {
+                TokenType::NIL => self.builder.build_return(None).unwrap(),
+                ty => self
+                    .builder
+                    .build_return(Some(&self.zero_value(ty)))
+                    .unwrap(),
+            };
+        }
+
+        self.variables = outer_variables;
+        self.loops = outer_loops;
+        if let Some(block) = outer_block {
+            self.builder.position_at_end(block);
         }
-        .unwrap();
-        let val = value;
+        Ok(())
+    }
+
+    fn create_entry_block_alloca(
+        &self,
+        func: FunctionValue<'ctx>,
+        ty: BasicTypeEnum<'ctx>,
+        name: &str,
+    ) -> PointerValue<'ctx> {
+        let entry_builder = self.context.create_builder();
+        let entry = func.get_first_basic_block().unwrap();
+        match entry.get_first_instruction() {
+            Some(instruction) => entry_builder.position_before(&instruction),
+            None => entry_builder.position_at_end(entry),
+        }
+        entry_builder.build_alloca(ty, name).unwrap()
+    }
 
+    pub fn build_print_call(&mut self, value: BasicValueEnum<'ctx>, typ: TokenType) {
+        let (format, name, args): (&str, &str, Vec<BasicMetadataValueEnum<'ctx>>) = match typ {
+            TokenType::FLOAT => ("%f\n", "fmt", vec![value.into()]),
+            TokenType::INT => ("%lld\n", "fmt_int", vec![value.into()]),
+            _ => {
+                let string = self.build_string_conversion(typ, value);
+                (
+                    "%.*s\n",
+                    "fmt_str",
+                    self.printf_string_args(string).to_vec(),
+                )
+            }
+        };
+        let format = self
+            .builder
+            .build_global_string_ptr(format, name)
+            .unwrap()
+            .as_pointer_value();
+
+        let mut values: Vec<BasicMetadataValueEnum<'ctx>> = vec![format.into()];
+        values.extend(args);
         self.builder
-            .build_call(
-                self.print_f,
-                &[format_str.as_pointer_value().into(), val.into()],
-                "printf",
-            )
+            .build_call(self.print_f, &values, "printf")
+            .unwrap();
+    }
+
+    // The length and bytes a `%.*s` conversion prints `string` from. Strings
+    // know their length, they don't need to end in a NUL.
+    fn printf_string_args(&self, string: StructValue<'ctx>) -> [BasicMetadataValueEnum<'ctx>; 2] {
+        let bytes = self
+            .builder
+            .build_extract_value(string, 0, "bytes")
+            .unwrap();
+        let len = self
+            .builder
+            .build_extract_value(string, 1, "len")
+            .unwrap()
+            .into_int_value();
+        let len = self
+            .builder
+            .build_int_truncate(len, self.context.i32_type(), "len")
             .unwrap();
+        [len.into(), bytes.into()]
     }
-    pub fn compile_expr(&mut self, expr: Expr) -> (TokenType, BasicValueEnum<'ctx>) {
-        match expr {
-            Expr::Assign { name, value }=>{
-                if let Some(name) = self.variables.get(&name.lexeme).cloned(){
-                    let val = self.builder.build_load(name.1, name.2, "val").unwrap();
-                    let expr = self.compile_expr(*value);
+
+    // `value` of type `ty` as a string, formatted the way `print` shows it
+    fn build_string_conversion(
+        &self,
+        ty: TokenType,
+        value: BasicValueEnum<'ctx>,
+    ) -> StructValue<'ctx> {
+        let function = match ty {
+            TokenType::STRING => return value.into_struct_value(),
+            TokenType::BOOL => {
+                let true_str = self.const_string("true");
+                let false_str = self.const_string("false");
+                return self
+                    .builder
+                    .build_select(value.into_int_value(), true_str, false_str, "bool_str")
+                    .unwrap()
+                    .into_struct_value();
+            }
+            TokenType::INT => runtime::from_int(self.context, &self.module),
+            TokenType::FLOAT => runtime::from_float(self.context, &self.module),
+            _ => panic!("cannot convert {:?} to a string", ty),
+        };
+        self.builder
+            .build_call(function, &[value.into()], "to_string")
+            .unwrap()
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_struct_value()
+    }
+
+    fn const_string(&self, value: &str) -> StructValue<'ctx> {
+        let bytes = self
+            .builder
+            .build_global_string_ptr(value, "str")
+            .unwrap()
+            .as_pointer_value();
+        let len = self.context.i64_type().const_int(value.len() as u64, false);
+        runtime::string_type(self.context).const_named_struct(&[bytes.into(), len.into()])
+    }
+
+    // the value a declaration without initializer starts out with
+    fn zero_value(&self, ty: TokenType) -> BasicValueEnum<'ctx> {
+        match ty {
+            TokenType::STRING => self.const_string("").into(),
+            ty => self.get_basic_type(ty).const_zero(),
+        }
+    }
+
+    pub fn compile_expr(
+        &mut self,
+        expr: Expr,
+    ) -> Result<(TokenType, BasicValueEnum<'ctx>), Diagnostic> {
+        Ok(match expr {
+            Expr::Assign { name, value, .. } => {
+                if let Some(name) = self.variables.get(&name.lexeme).cloned() {
+                    let expr = self.compile_expr(*value)?;
                     self.builder.build_store(name.2, expr.1).unwrap();
-                    (name.0,val)
-                }else {
-                    panic!("uknown variable");
+                    (name.0, expr.1)
+                } else {
+                    return Err(self.error(
+                        name.span,
+                        format!("undefined variable '{}'", name.lexeme),
+                    ));
                 }
             }
-            Expr::Literal { value } => self.compile_value(value),
-            Expr::Grouping { expression } => self.compile_expr(*expression),
-            Expr::Unary { operator, right } => {
-                let val = self.compile_expr(*right);
-                let ty = val.1.get_type();
-                match (operator.token_type, ty) {
-                    (TokenType::MINUS, BasicTypeEnum::FloatType(a)) => {
-                        let mul = self.context.f64_type().const_float(-1.0);
-                        let value = val.1.into_float_value();
-                        let value = self
-                            .builder
-                            .build_float_mul(value, mul.into(), "mul")
-                            .unwrap();
-                        (TokenType::INT, value.into())
+            Expr::Literal { value, span } => self.compile_value(value, span)?,
+            Expr::Grouping { expression, .. } => self.compile_expr(*expression)?,
+            Expr::Unary {
+                operator, right, ..
+            } => {
+                let (ty, value) = self.compile_expr(*right)?;
+                let value: BasicValueEnum<'ctx> = match (operator.token_type, ty) {
+                    (TokenType::MINUS, TokenType::FLOAT) => self
+                        .builder
+                        .build_float_neg(value.into_float_value(), "neg")
+                        .unwrap()
+                        .into(),
+                    (TokenType::MINUS, TokenType::INT) => self
+                        .builder
+                        .build_int_neg(value.into_int_value(), "neg")
+                        .unwrap()
+                        .into(),
+                    (TokenType::BANG, TokenType::BOOL) => self
+                        .builder
+                        .build_not(value.into_int_value(), "not")
+                        .unwrap()
+                        .into(),
+                    _ => {
+                        return Err(self.error(
+                            operator.span,
+                            format!("cannot apply '{}' to {:?}", operator.lexeme, ty),
+                        ));
+                    }
+                };
+                (ty, value)
+            }
+            Expr::Interpolated { parts, .. } => {
+                // one snprintf call with a conversion for each part that isn't text
+                let mut format = String::new();
+                let mut args: Vec<BasicMetadataValueEnum<'ctx>> = vec![];
+                for part in parts {
+                    if let Expr::Literal {
+                        value: LiteralValue::StringValue(text),
+                        ..
+                    } = &part
+                    {
+                        format.push_str(&text.replace('%', "%%"));
+                        continue;
                     }
-                    _ => panic!("error 1"),
+                    let (ty, value) = self.compile_expr(part)?;
+                    match ty {
+                        TokenType::INT => format.push_str("%lld"),
+                        TokenType::FLOAT => format.push_str("%f"),
+                        _ => {
+                            let string = self.build_string_conversion(ty, value);
+                            format.push_str("%.*s");
+                            args.extend(self.printf_string_args(string));
+                            continue;
+                        }
+                    }
+                    args.push(value.into());
                 }
+                let string = runtime::build_format(
+                    self.context,
+                    &self.module,
+                    &self.builder,
+                    &format,
+                    &args,
+                );
+                (TokenType::STRING, string.into())
+            }
+            Expr::Cast {
+                expression, target, ..
+            } => {
+                let value = self.compile_expr(*expression)?;
+                self.compile_cast(value, &target)?
             }
-            Expr::Variable { name } => {
+            Expr::Variable { name, .. } => {
                 if let Some(a) = self.variables.get(&name.lexeme) {
                     let loaded = self.builder.build_load(a.1, a.2, "var").unwrap();
                     (a.0, loaded)
                 } else {
-                    panic!("no such variable");
+                    return Err(self.error(
+                        name.span,
+                        format!("undefined variable '{}'", name.lexeme),
+                    ));
                 }
             }
+            Expr::Call { callie, args, .. } => {
+                let Expr::Variable { name, .. } = *callie else {
+                    return Err(self.error(callie.span(), "can only call functions"));
+                };
+                let Some((function, param_types, return_type)) =
+                    self.functions.get(&name.lexeme).cloned()
+                else {
+                    if let Some(builtin) = Builtin::lookup(&name.lexeme) {
+                        return self.compile_builtin(builtin, &name, args);
+                    }
+                    return Err(self.error(
+                        name.span,
+                        format!("undefined function '{}'", name.lexeme),
+                    ));
+                };
+                if param_types.len() != args.len() {
+                    return Err(self.error(
+                        name.span,
+                        format!(
+                            "{} expects {} arguments but got {}",
+                            name.lexeme,
+                            param_types.len(),
+                            args.len()
+                        ),
+                    ));
+                }
+
+                let mut values: Vec<BasicMetadataValueEnum<'ctx>> = vec![];
+                for (arg, param_type) in args.into_iter().zip(param_types) {
+                    let arg_span = arg.span();
+                    let value = self.compile_expr(arg)?;
+                    if value.1.get_type() != self.get_basic_type(param_type) {
+                        return Err(self.error(
+                            arg_span,
+                            format!(
+                                "{} expects {:?} argument but got {:?}",
+                                name.lexeme, param_type, value.0
+                            ),
+                        ));
+                    }
+                    values.push(value.1.into());
+                }
+                let call = self.builder.build_call(function, &values, "call").unwrap();
+                match call.try_as_basic_value().left() {
+                    Some(value) => (return_type, value),
+                    // void calls only make sense as statements, hand back a placeholder
+                    None => (TokenType::NIL, self.context.bool_type().const_zero().into()),
+                }
+            }
+            Expr::Logical {
+                expression,
+                operator,
+                right,
+                ..
+            } => {
+                let bool_type = self.context.bool_type();
+                let left = self.compile_expr(*expression)?;
+                if left.1.get_type() != bool_type.into() {
+                    return Err(self.error(
+                        operator.span,
+                        format!("left operand of '{}' must be a bool", operator.lexeme),
+                    ));
+                }
+
+                // the left operand may have opened new blocks, branch from wherever it ended
+                let left_block = self.builder.get_insert_block().unwrap();
+                let func = left_block.get_parent().unwrap();
+                let rhs_block = self.context.append_basic_block(func, "logical_rhs");
+                let merge_block = self.context.append_basic_block(func, "logical_merge");
+
+                // `and` only looks at the right side when the left is true, `or` when it is false
+                let (on_true, on_false) = match operator.token_type {
+                    TokenType::AND => (rhs_block, merge_block),
+                    TokenType::OR => (merge_block, rhs_block),
+                    _ => panic!("unknown logical operator {}", operator.lexeme),
+                };
+                self.builder
+                    .build_conditional_branch(left.1.into_int_value(), on_true, on_false)
+                    .unwrap();
+
+                self.builder.position_at_end(rhs_block);
+                let right = self.compile_expr(*right)?;
+                if right.1.get_type() != bool_type.into() {
+                    return Err(self.error(
+                        operator.span,
+                        format!("right operand of '{}' must be a bool", operator.lexeme),
+                    ));
+                }
+                let rhs_block = self.builder.get_insert_block().unwrap();
+                self.builder
+                    .build_unconditional_branch(merge_block)
+                    .unwrap();
+
+                self.builder.position_at_end(merge_block);
+                let phi = self.builder.build_phi(bool_type, "logical").unwrap();
+                phi.add_incoming(&[(&left.1, left_block), (&right.1, rhs_block)]);
+                (TokenType::BOOL, phi.as_basic_value())
+            }
             Expr::Binary {
                 left,
                 operator,
                 right,
+                ..
             } => {
-                let left = self.compile_expr(*left);
-                let right = self.compile_expr(*right);
+                let left = self.compile_expr(*left)?;
+                let right = self.compile_expr(*right)?;
+
+                // bools are ints to LLVM as well, so go by the tags
+                match (left.0, right.0) {
+                    (TokenType::INT, TokenType::INT) => {
+                        return self.compile_int_binary(
+                            &operator,
+                            left.1.into_int_value(),
+                            right.1.into_int_value(),
+                        );
+                    }
+                    (TokenType::BOOL, TokenType::BOOL)
+                        if matches!(
+                            operator.token_type,
+                            TokenType::EQUAL_EQUAL | TokenType::BANG_EQUAL
+                        ) =>
+                    {
+                        let predicate = comparison_predicate(operator.token_type).unwrap();
+                        let value = self
+                            .builder
+                            .build_int_compare(
+                                predicate,
+                                left.1.into_int_value(),
+                                right.1.into_int_value(),
+                                "cmp_temp",
+                            )
+                            .unwrap();
+                        return Ok((TokenType::BOOL, value.into()));
+                    }
+                    (TokenType::STRING, TokenType::STRING) => {
+                        return self.compile_string_binary(&operator, left.1, right.1);
+                    }
+                    _ => (),
+                }
 
                 match (left.1.get_type(), operator.token_type, right.1.get_type()) {
                     (BasicTypeEnum::FloatType(a), TokenType::PLUS, BasicTypeEnum::FloatType(b)) => {
                         (
-                            right.0,
+                            TokenType::FLOAT,
                             self.builder
                                 .build_float_add(
                                     left.1.into_float_value(),
@@ -301,7 +882,7 @@ impl<'ctx> Compiler<'ctx> {
                         TokenType::MINUS,
                         BasicTypeEnum::FloatType(b),
                     ) => (
-                        right.0,
+                        TokenType::FLOAT,
                         self.builder
                             .build_float_sub(
                                 left.1.into_float_value(),
@@ -313,7 +894,7 @@ impl<'ctx> Compiler<'ctx> {
                     ),
                     (BasicTypeEnum::FloatType(a), TokenType::STAR, BasicTypeEnum::FloatType(b)) => {
                         (
-                            right.0,
+                            TokenType::FLOAT,
                             self.builder
                                 .build_float_mul(
                                     left.1.into_float_value(),
@@ -329,7 +910,7 @@ impl<'ctx> Compiler<'ctx> {
                         TokenType::SLASH,
                         BasicTypeEnum::FloatType(b),
                     ) => (
-                        right.0,
+                        TokenType::FLOAT,
                         self.builder
                             .build_float_div(
                                 left.1.into_float_value(),
@@ -339,12 +920,34 @@ impl<'ctx> Compiler<'ctx> {
                             .unwrap()
                             .into(),
                     ),
+                    (
+                        BasicTypeEnum::FloatType(a),
+                        TokenType::Modulus,
+                        BasicTypeEnum::FloatType(b),
+                    ) => {
+                        // frem becomes a call to libm's fmod anyway, calling it
+                        // ourselves lets the JIT bind it like the other libc functions
+                        let f64_type = self.context.f64_type();
+                        let fmod = runtime::libc_function(
+                            &self.module,
+                            "fmod",
+                            f64_type.fn_type(&[f64_type.into(), f64_type.into()], false),
+                        );
+                        let rem = self
+                            .builder
+                            .build_call(fmod, &[left.1.into(), right.1.into()], "rem_temp")
+                            .unwrap()
+                            .try_as_basic_value()
+                            .left()
+                            .unwrap();
+                        (TokenType::FLOAT, rem)
+                    }
                     (
                         BasicTypeEnum::FloatType(a),
                         TokenType::GREATER,
                         BasicTypeEnum::FloatType(b),
                     ) => (
-                        right.0,
+                        TokenType::BOOL,
                         self.builder
                             .build_float_compare(
                                 inkwell::FloatPredicate::OGT,
@@ -357,7 +960,7 @@ impl<'ctx> Compiler<'ctx> {
                     ),
                     (BasicTypeEnum::FloatType(a), TokenType::LESS, BasicTypeEnum::FloatType(b)) => {
                         (
-                            right.0,
+                            TokenType::BOOL,
                             self.builder
                                 .build_float_compare(
                                     inkwell::FloatPredicate::OLT,
@@ -374,7 +977,7 @@ impl<'ctx> Compiler<'ctx> {
                         TokenType::LESS_EQUAL,
                         BasicTypeEnum::FloatType(b),
                     ) => (
-                        right.0,
+                        TokenType::BOOL,
                         self.builder
                             .build_float_compare(
                                 inkwell::FloatPredicate::OLE,
@@ -390,7 +993,7 @@ impl<'ctx> Compiler<'ctx> {
                         TokenType::GREATER_EQUAL,
                         BasicTypeEnum::FloatType(b),
                     ) => (
-                        right.0,
+                        TokenType::BOOL,
                         self.builder
                             .build_float_compare(
                                 inkwell::FloatPredicate::OGE,
@@ -403,10 +1006,10 @@ impl<'ctx> Compiler<'ctx> {
                     ),
                     (
                         BasicTypeEnum::FloatType(a),
-                        TokenType::EQUAL,
+                        TokenType::EQUAL_EQUAL,
                         BasicTypeEnum::FloatType(b),
                     ) => (
-                        right.0,
+                        TokenType::BOOL,
                         self.builder
                             .build_float_compare(
                                 inkwell::FloatPredicate::OEQ,
@@ -422,10 +1025,11 @@ impl<'ctx> Compiler<'ctx> {
                         TokenType::BANG_EQUAL,
                         BasicTypeEnum::FloatType(b),
                     ) => (
-                        right.0,
+                        TokenType::BOOL,
                         self.builder
                             .build_float_compare(
-                                inkwell::FloatPredicate::ONE,
+                                // unordered, so nan != nan like everywhere else
+                                inkwell::FloatPredicate::UNE,
                                 left.1.into_float_value(),
                                 right.1.into_float_value(),
                                 "div_temp",
@@ -433,18 +1037,240 @@ impl<'ctx> Compiler<'ctx> {
                             .unwrap()
                             .into(),
                     ),
-                    _ => panic!("error unmatches types"),
+                    _ => {
+                        return Err(self.error(
+                            operator.span,
+                            format!(
+                                "cannot apply '{}' to {:?} and {:?}",
+                                operator.lexeme, left.0, right.0
+                            ),
+                        ));
+                    }
                 }
             }
+        })
+    }
 
-            _ => panic!("error unmatches types"),
+    fn compile_builtin(
+        &mut self,
+        builtin: Builtin,
+        name: &Token,
+        args: Vec<Expr>,
+    ) -> Result<(TokenType, BasicValueEnum<'ctx>), Diagnostic> {
+        if args.len() != builtin.params().len() {
+            return Err(self.error(
+                name.span,
+                format!(
+                    "{} expects {} arguments but got {}",
+                    name.lexeme,
+                    builtin.params().len(),
+                    args.len()
+                ),
+            ));
         }
+        let mut values = vec![];
+        for arg in args {
+            values.push(self.compile_expr(arg)?.1);
+        }
+
+        let string = values[0].into_struct_value();
+        let bytes = self
+            .builder
+            .build_extract_value(string, 0, "bytes")
+            .unwrap()
+            .into_pointer_value();
+        let len = self
+            .builder
+            .build_extract_value(string, 1, "len")
+            .unwrap()
+            .into_int_value();
+        Ok(match builtin {
+            Builtin::Len => (TokenType::INT, len.into()),
+            Builtin::Substring => {
+                // the result shares the bytes of the string it is cut from
+                let (start, end) = (values[1].into_int_value(), values[2].into_int_value());
+                let builder = &self.builder;
+                let zero = self.context.i64_type().const_zero();
+                let conditions = [
+                    builder.build_int_compare(IntPredicate::SLT, start, zero, "before_start"),
+                    builder.build_int_compare(IntPredicate::SLT, end, start, "backwards"),
+                    builder.build_int_compare(IntPredicate::SGT, end, len, "past_end"),
+                ];
+                let mut out_of_range = self.context.bool_type().const_zero();
+                for condition in conditions {
+                    out_of_range = builder
+                        .build_or(out_of_range, condition.unwrap(), "out_of_range")
+                        .unwrap();
+                }
+                self.build_runtime_check(out_of_range, "substring out of range", name.span);
+
+                // lengths count bytes, but a cut may not split a character
+                let is_char_boundary = runtime::is_char_boundary(self.context, &self.module);
+                let mut splits_char = self.context.bool_type().const_zero();
+                for index in [start, end] {
+                    let on_boundary = self
+                        .builder
+                        .build_call(
+                            is_char_boundary,
+                            &[values[0].into(), index.into()],
+                            "boundary",
+                        )
+                        .unwrap()
+                        .try_as_basic_value()
+                        .left()
+                        .unwrap()
+                        .into_int_value();
+                    let splits = self.builder.build_not(on_boundary, "splits").unwrap();
+                    splits_char = self
+                        .builder
+                        .build_or(splits_char, splits, "splits_char")
+                        .unwrap();
+                }
+                self.build_runtime_check(
+                    splits_char,
+                    "substring cuts a character in half",
+                    name.span,
+                );
+
+                let bytes = unsafe {
+                    self.builder
+                        .build_gep(self.context.i8_type(), bytes, &[start], "bytes")
+                        .unwrap()
+                };
+                let len = self.builder.build_int_sub(end, start, "len").unwrap();
+                let value = runtime::build_string(
+                    &self.builder,
+                    runtime::string_type(self.context),
+                    bytes.into(),
+                    len.into(),
+                );
+                (TokenType::STRING, value.into())
+            }
+        })
     }
 
-    pub fn compile_value(&self, value: LiteralValue) -> (TokenType, BasicValueEnum<'ctx>) {
-        match value {
-            LiteralValue::Number(a) => (
+    fn compile_cast(
+        &self,
+        value: (TokenType, BasicValueEnum<'ctx>),
+        target: &Token,
+    ) -> Result<(TokenType, BasicValueEnum<'ctx>), Diagnostic> {
+        let i64_type = self.context.i64_type();
+        let converted: BasicValueEnum<'ctx> = match (value.0, target.token_type) {
+            (from, to) if from == to => value.1,
+            (TokenType::INT, TokenType::FLOAT) => self
+                .builder
+                .build_signed_int_to_float(
+                    value.1.into_int_value(),
+                    self.context.f64_type(),
+                    "itof",
+                )
+                .unwrap()
+                .into(),
+            (TokenType::FLOAT, TokenType::INT) => {
+                // a plain fptosi is poison for nan and out of range values, the
+                // saturating version clamps them (and makes nan 0) instead
+                let fptosi = Intrinsic::find("llvm.fptosi.sat").unwrap();
+                let function = fptosi
+                    .get_declaration(
+                        &self.module,
+                        &[i64_type.into(), self.context.f64_type().into()],
+                    )
+                    .unwrap();
+                self.builder
+                    .build_call(function, &[value.1.into()], "ftoi")
+                    .unwrap()
+                    .try_as_basic_value()
+                    .left()
+                    .unwrap()
+            }
+            (TokenType::BOOL, TokenType::INT) => self
+                .builder
+                .build_int_z_extend(value.1.into_int_value(), i64_type, "btoi")
+                .unwrap()
+                .into(),
+            (TokenType::INT | TokenType::FLOAT | TokenType::BOOL, TokenType::STRING) => {
+                self.build_string_conversion(value.0, value.1).into()
+            }
+            (from, to) => {
+                return Err(self.error(target.span, format!("cannot cast {:?} to {:?}", from, to)));
+            }
+        };
+        Ok((target.token_type, converted))
+    }
+
+    fn compile_int_binary(
+        &self,
+        operator: &Token,
+        left: IntValue<'ctx>,
+        right: IntValue<'ctx>,
+    ) -> Result<(TokenType, BasicValueEnum<'ctx>), Diagnostic> {
+        let builder = &self.builder;
+        let value = match operator.token_type {
+            TokenType::PLUS => builder.build_int_add(left, right, "add_temp"),
+            TokenType::MINUS => builder.build_int_sub(left, right, "sub_temp"),
+            TokenType::STAR => builder.build_int_mul(left, right, "mul_temp"),
+            TokenType::SLASH | TokenType::Modulus => {
+                let is_zero = builder
+                    .build_int_compare(
+                        IntPredicate::EQ,
+                        right,
+                        right.get_type().const_zero(),
+                        "is_zero",
+                    )
+                    .unwrap();
+                self.build_runtime_check(is_zero, "division by zero", operator.span);
+                // i64::MIN / -1 overflows, which LLVM leaves undefined. Divide
+                // by 1 instead and fix the result up so it wraps like `+` and `*`
+                let minus_one = self.context.i64_type().const_all_ones();
+                let is_minus_one = builder
+                    .build_int_compare(IntPredicate::EQ, right, minus_one, "is_minus_one")
+                    .unwrap();
+                let one = self.context.i64_type().const_int(1, false);
+                let divisor = builder
+                    .build_select(is_minus_one, one, right, "divisor")
+                    .unwrap()
+                    .into_int_value();
+                if operator.token_type == TokenType::Modulus {
+                    // x % 1 is 0, which is also what x % -1 is
+                    builder.build_int_signed_rem(left, divisor, "rem_temp")
+                } else {
+                    let quotient = builder
+                        .build_int_signed_div(left, divisor, "div_temp")
+                        .unwrap();
+                    let negated = builder.build_int_neg(left, "neg").unwrap();
+                    builder
+                        .build_select(is_minus_one, negated, quotient, "div_temp")
+                        .map(|value| value.into_int_value())
+                }
+            }
+            _ => {
+                let Some(predicate) = comparison_predicate(operator.token_type) else {
+                    return Err(self.error(
+                        operator.span,
+                        format!("cannot apply '{}' to INT and INT", operator.lexeme),
+                    ));
+                };
+                let value = builder
+                    .build_int_compare(predicate, left, right, "cmp_temp")
+                    .unwrap();
+                return Ok((TokenType::BOOL, value.into()));
+            }
+        };
+        Ok((TokenType::INT, value.unwrap().into()))
+    }
+
+    pub fn compile_value(
+        &self,
+        value: LiteralValue,
+        span: Span,
+    ) -> Result<(TokenType, BasicValueEnum<'ctx>), Diagnostic> {
+        Ok(match value {
+            LiteralValue::Int(a) => (
                 TokenType::INT,
+                self.context.i64_type().const_int(a as u64, true).into(),
+            ),
+            LiteralValue::Number(a) => (
+                TokenType::FLOAT,
                 self.context.f64_type().const_float(a).into(),
             ),
             LiteralValue::True => (
@@ -455,17 +1281,11 @@ impl<'ctx> Compiler<'ctx> {
                 TokenType::BOOL,
                 self.context.bool_type().const_int(0, false).into(),
             ),
-            LiteralValue::StringValue(a) => {
-                let str_val = self.builder.build_global_string_ptr(&a, "str");
-                (
-                    TokenType::STRING,
-                    str_val.unwrap().as_pointer_value().into(),
-                )
-            }
-            _ => {
-                panic!("uknown type");
+            LiteralValue::StringValue(a) => (TokenType::STRING, self.const_string(&a).into()),
+            LiteralValue::Nil => {
+                return Err(self.error(span, "nil can't be used as a value"));
             }
-        }
+        })
     }
 
     fn load_if_pointer(&mut self, value: BasicValueEnum<'ctx>) -> BasicValueEnum<'ctx> {
@@ -479,14 +1299,131 @@ impl<'ctx> Compiler<'ctx> {
             other => other,
         }
     }
+    // Strings concatenate with `+` and compare by content, byte by byte.
+    fn compile_string_binary(
+        &self,
+        operator: &Token,
+        left: BasicValueEnum<'ctx>,
+        right: BasicValueEnum<'ctx>,
+    ) -> Result<(TokenType, BasicValueEnum<'ctx>), Diagnostic> {
+        if operator.token_type == TokenType::PLUS {
+            let concat = runtime::concat(self.context, &self.module);
+            let value = self
+                .builder
+                .build_call(concat, &[left.into(), right.into()], "concat")
+                .unwrap()
+                .try_as_basic_value()
+                .left()
+                .unwrap();
+            return Ok((TokenType::STRING, value));
+        }
+        let Some(predicate) = comparison_predicate(operator.token_type) else {
+            return Err(self.error(
+                operator.span,
+                format!("cannot apply '{}' to STRING and STRING", operator.lexeme),
+            ));
+        };
+        let compare = runtime::compare(self.context, &self.module);
+        let order = self
+            .builder
+            .build_call(compare, &[left.into(), right.into()], "compare")
+            .unwrap()
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value();
+        // the sign of the comparison orders the strings the same way the operator does
+        let value = self
+            .builder
+            .build_int_compare(
+                predicate,
+                order,
+                self.context.i32_type().const_zero(),
+                "cmp_temp",
+            )
+            .unwrap();
+        Ok((TokenType::BOOL, value.into()))
+    }
+
+    // Branches to code that reports `message` at `span` and exits the
+    // program when `failed` is true, and continues in a new block otherwise.
+    fn build_runtime_check(&self, failed: IntValue<'ctx>, message: &str, span: Span) {
+        let func = self
+            .builder
+            .get_insert_block()
+            .unwrap()
+            .get_parent()
+            .unwrap();
+        let fail_block = self.context.append_basic_block(func, "check_failed");
+        let ok_block = self.context.append_basic_block(func, "check_ok");
+        self.builder
+            .build_conditional_branch(failed, fail_block, ok_block)
+            .unwrap();
+
+        self.builder.position_at_end(fail_block);
+        let i32_type = self.context.i32_type();
+        let ptr_type = self.context.ptr_type(AddressSpace::default());
+        let dprintf = runtime::libc_function(
+            &self.module,
+            "dprintf",
+            i32_type.fn_type(&[i32_type.into(), ptr_type.into()], true),
+        );
+        let exit = runtime::libc_function(
+            &self.module,
+            "exit",
+            self.context.void_type().fn_type(&[i32_type.into()], false),
+        );
+        let message = self
+            .builder
+            .build_global_string_ptr(
+                &format!(
+                    "error: {} at line {}, column {}\n",
+                    message, span.line, span.column
+                ),
+                "error_msg",
+            )
+            .unwrap();
+        let stderr = i32_type.const_int(2, false);
+        self.builder
+            .build_call(
+                dprintf,
+                &[stderr.into(), message.as_pointer_value().into()],
+                "",
+            )
+            .unwrap();
+        let code = i32_type.const_int(EXIT_INTERNAL as u64, false);
+        self.builder.build_call(exit, &[code.into()], "").unwrap();
+        self.builder.build_unreachable().unwrap();
+
+        self.builder.position_at_end(ok_block);
+    }
+
+    fn error(&self, span: Span, msg: impl Into<String>) -> Diagnostic {
+        Diagnostic::error(span, msg).with_code(CODEGEN_ERROR)
+    }
+
     fn get_basic_type(&self, ty: TokenType) -> BasicTypeEnum<'ctx> {
         match ty {
+            TokenType::INT => self.context.i64_type().into(),
             TokenType::FLOAT => self.context.f64_type().into(),
             TokenType::BOOL => self.context.bool_type().into(),
-            TokenType::STRING => self.context.ptr_type(AddressSpace::default()).into(),
+            TokenType::STRING => runtime::string_type(self.context).into(),
             _ => {
                 panic!("invvalid type");
             }
         }
     }
 }
+
+// the signed integer comparison an operator stands for
+fn comparison_predicate(operator: TokenType) -> Option<IntPredicate> {
+    Some(match operator {
+        TokenType::EQUAL_EQUAL => IntPredicate::EQ,
+        TokenType::BANG_EQUAL => IntPredicate::NE,
+        TokenType::GREATER => IntPredicate::SGT,
+        TokenType::GREATER_EQUAL => IntPredicate::SGE,
+        TokenType::LESS => IntPredicate::SLT,
+        TokenType::LESS_EQUAL => IntPredicate::SLE,
+        _ => return None,
+    })
+}
@@ -0,0 +1,194 @@
+use std::fmt::Display;
+
+use crate::token::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+// A span in the source with an optional message printed next to its underline.
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+// Error codes, grouped by the stage that reports them.
+pub const SYNTAX_ERROR: &str = "E0001";
+pub const UNKNOWN_CHARACTER: &str = "E0002";
+pub const UNTERMINATED_STRING: &str = "E0003";
+pub const INVALID_NUMBER: &str = "E0004";
+pub const INVALID_ESCAPE: &str = "E0005";
+pub const UNTERMINATED_COMMENT: &str = "E0006";
+pub const UNDEFINED_NAME: &str = "E0101";
+pub const TYPE_MISMATCH: &str = "E0102";
+pub const ARITY_MISMATCH: &str = "E0103";
+pub const INVALID_OPERAND: &str = "E0104";
+pub const INVALID_CONTROL_FLOW: &str = "E0105";
+pub const INVALID_CAST: &str = "E0106";
+pub const DUPLICATE_DEFINITION: &str = "E0107";
+pub const DIVISION_BY_ZERO: &str = "E0108";
+pub const CODEGEN_ERROR: &str = "E0201";
+pub const RUNTIME_ERROR: &str = "E0301";
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub primary: Label,
+    pub secondary: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(span: Span, message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, span, message)
+    }
+
+    pub fn warning(span: Span, message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, span, message)
+    }
+
+    fn new(severity: Severity, span: Span, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            code: None,
+            message: message.into(),
+            primary: Label {
+                span,
+                message: String::new(),
+            },
+            secondary: vec![],
+            notes: vec![],
+            help: None,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    // message shown under the primary span
+    pub fn with_label(mut self, message: impl Into<String>) -> Self {
+        self.primary.message = message.into();
+        self
+    }
+
+    pub fn with_secondary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.secondary.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Renders the diagnostic rustc style, with the offending source lines
+    /// and carets under the labelled spans:
+    ///
+    /// ```text
+    /// error[E0102]: cannot initialize 'a' of type float with a value of type string
+    ///  --> test.lox:1:11
+    ///   |
+    /// 1 | float a = "x";
+    ///   |           ^^^ expected float
+    /// ```
+    pub fn render(&self, path: &str, source: &str) -> String {
+        let mut out = match self.code {
+            Some(code) => format!("{}[{}]: {}\n", self.severity, code, self.message),
+            None => format!("{}: {}\n", self.severity, self.message),
+        };
+
+        let mut labels: Vec<(&Label, bool)> = vec![(&self.primary, true)];
+        labels.extend(self.secondary.iter().map(|label| (label, false)));
+        labels.sort_by_key(|(label, _)| label.span.start);
+
+        let width = labels
+            .iter()
+            .map(|(label, _)| label.span.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let pad = " ".repeat(width);
+
+        out.push_str(&format!("{}--> {}:{}\n", pad, path, self.primary.span));
+        out.push_str(&format!("{} |\n", pad));
+
+        let mut last_line = None;
+        for (label, is_primary) in labels {
+            let (line_start, text) = source_line(source, label.span.start);
+            if last_line != Some(label.span.line) {
+                out.push_str(&format!(
+                    "{:>width$} | {}\n",
+                    label.span.line,
+                    text,
+                    width = width
+                ));
+                last_line = Some(label.span.line);
+            }
+
+            // keep tabs so the carets line up with the source line above them
+            let offset = label.span.start.saturating_sub(line_start).min(text.len());
+            let indent: String = text[..offset]
+                .chars()
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let end = label.span.end.saturating_sub(line_start).min(text.len());
+            let len = text.get(offset..end).map_or(0, |s| s.chars().count()).max(1);
+            let marker = if is_primary { "^" } else { "-" };
+            let underline = format!("{}{}", indent, marker.repeat(len));
+            if label.message.is_empty() {
+                out.push_str(&format!("{} | {}\n", pad, underline));
+            } else {
+                out.push_str(&format!("{} | {} {}\n", pad, underline, label.message));
+            }
+        }
+
+        if !self.notes.is_empty() || self.help.is_some() {
+            out.push_str(&format!("{} |\n", pad));
+        }
+        for note in &self.notes {
+            out.push_str(&format!("{} = note: {}\n", pad, note));
+        }
+        if let Some(help) = &self.help {
+            out.push_str(&format!("{} = help: {}\n", pad, help));
+        }
+        out
+    }
+}
+
+// the line containing byte `offset`, along with the offset that line starts at
+fn source_line(source: &str, offset: usize) -> (usize, &str) {
+    let offset = offset.min(source.len());
+    let start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let end = source[offset..]
+        .find('\n')
+        .map_or(source.len(), |i| offset + i);
+    (start, source[start..end].trim_end_matches('\r'))
+}
@@ -6,11 +6,12 @@ use std::{
 };
 
 use crate::{
-    token::{Literal, Token},
+    token::{Literal, Span, Token},
     tokentype::TokenType,
 };
 #[derive(Clone)]
 pub enum LiteralValue {
+    Int(i64),
     Number(f64),
     StringValue(String),
     True,
@@ -26,6 +27,7 @@ impl Debug for LiteralValue {
 impl PartialEq for LiteralValue {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
+            (Self::Int(a), Self::Int(b)) => a == b,
             (Self::Number(a), Self::Number(b)) => a == b,
             (Self::StringValue(a), Self::StringValue(b)) => a == b,
             (Self::True, Self::True) => true,
@@ -42,33 +44,13 @@ impl LiteralValue {
         }
     }
 
-    pub fn is_falsy(&self) -> LiteralValue {
-        match self {
-            Self::Number(x) => {
-                if *x == 0.0 {
-                    Self::True
-                } else {
-                    Self::False
-                }
-            }
-            Self::StringValue(s) => {
-                if s.len() == 0 {
-                    Self::True
-                } else {
-                    Self::False
-                }
-            }
-            Self::True => Self::False,
-            Self::False => Self::True,
-            Self::Nil => Self::True,
-        }
-    }
     pub fn from_bool(b: bool) -> Self {
         if b { Self::True } else { Self::False }
     }
 
     pub fn to_type(&self) -> String {
         match self {
+            LiteralValue::Int(_) => "Int".to_string(),
             LiteralValue::Number(_) => "Number".to_string(),
             LiteralValue::StringValue(_) => "String".to_string(),
             LiteralValue::Nil => "nil".to_string(),
@@ -83,41 +65,80 @@ pub enum Expr {
     Assign {
         name: Token,
         value: Box<Expr>,
+        span: Span,
     },
     Call {
         callie: Box<Expr>,
         paren: Token,
         args: Vec<Expr>,
+        span: Span,
     },
     Binary {
         left: Box<Expr>,
         operator: Token,
         right: Box<Expr>,
+        span: Span,
     },
     Grouping {
         expression: Box<Expr>,
+        span: Span,
     },
     Literal {
         value: LiteralValue,
+        span: Span,
     },
     Unary {
         operator: Token,
         right: Box<Expr>,
+        span: Span,
     },
     Variable {
         name: Token,
+        span: Span,
     },
     Logical {
         expression: Box<Expr>,
         operator: Token,
         right: Box<Expr>,
+        span: Span,
+    },
+    // a string literal with `${...}` in it, the text between them is kept
+    // as string literals
+    Interpolated {
+        parts: Vec<Expr>,
+        span: Span,
+    },
+    Cast {
+        expression: Box<Expr>,
+        keyword: Token,
+        // the type keyword after `as`
+        target: Token,
+        span: Span,
     },
 }
 
+impl Expr {
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Assign { span, .. }
+            | Expr::Call { span, .. }
+            | Expr::Binary { span, .. }
+            | Expr::Grouping { span, .. }
+            | Expr::Literal { span, .. }
+            | Expr::Unary { span, .. }
+            | Expr::Variable { span, .. }
+            | Expr::Logical { span, .. }
+            | Expr::Interpolated { span, .. }
+            | Expr::Cast { span, .. } => *span,
+        }
+    }
+}
+
 #[allow(warnings)]
 impl ToString for LiteralValue {
     fn to_string(&self) -> String {
         match self {
+            LiteralValue::Int(x) => x.to_string(),
             LiteralValue::Number(x) => x.to_string(),
             LiteralValue::StringValue(x) => x.clone(),
             LiteralValue::True => "true".to_string(),
@@ -133,9 +154,10 @@ fn unwrap_as_string(literal: Option<Literal>) -> String {
         _ => panic!("could not unwrap"),
     }
 }
-fn unwrap_as_f64(literal: Option<Literal>) -> f64 {
+fn unwrap_as_number(literal: Option<Literal>) -> LiteralValue {
     match literal {
-        Some(Literal::FLiteral(x)) => x as f64,
+        Some(Literal::FLiteral(x)) => LiteralValue::Number(x),
+        Some(Literal::ILiteral(x)) => LiteralValue::Int(x),
         _ => panic!("could not unwrap"),
     }
 }
@@ -143,7 +165,7 @@ fn unwrap_as_f64(literal: Option<Literal>) -> f64 {
 impl LiteralValue {
     pub fn from_token(token: Token) -> Self {
         match token.token_type {
-            crate::tokentype::TokenType::NUMBER => Self::Number(unwrap_as_f64(token.literal)),
+            crate::tokentype::TokenType::NUMBER => unwrap_as_number(token.literal),
             crate::tokentype::TokenType::STRINGLIT => {
                 Self::StringValue(unwrap_as_string(token.literal))
             }
@@ -162,6 +184,7 @@ impl ToString for Expr {
                 callie,
                 paren,
                 args,
+                ..
             } => {
                 format!("{:?}", callie)
             }
@@ -169,14 +192,16 @@ impl ToString for Expr {
                 expression,
                 operator,
                 right,
+                ..
             } => "".to_string(),
-            Expr::Assign { name, value } => {
+            Expr::Assign { name, value, .. } => {
                 format!("{name:?} = {}", value.to_string())
             }
             Expr::Binary {
                 left,
                 operator,
                 right,
+                ..
             } => {
                 format!(
                     "({} {} {})",
@@ -185,18 +210,27 @@ impl ToString for Expr {
                     right.to_string()
                 )
             }
-            Expr::Unary { operator, right } => {
+            Expr::Unary {
+                operator, right, ..
+            } => {
                 let operator_str = operator.lexeme.clone();
                 let right_str = (*right).to_string();
                 format!("({} {})", operator_str, right_str)
             }
-            Expr::Literal { value } => {
+            Expr::Literal { value, .. } => {
                 format!("{}", value.to_string())
             }
-            Expr::Grouping { expression } => {
+            Expr::Grouping { expression, .. } => {
                 format!("(group {})", (*expression).to_string())
             }
-            Expr::Variable { name } => format!("(var {})", name.lexeme),
+            Expr::Variable { name, .. } => format!("(var {})", name.lexeme),
+            Expr::Interpolated { parts, .. } => {
+                let parts: Vec<String> = parts.iter().map(|part| part.to_string()).collect();
+                format!("(interpolate {})", parts.join(" "))
+            }
+            Expr::Cast {
+                expression, target, ..
+            } => format!("(as {} {})", target.lexeme, expression.to_string()),
         }
     }
 }
@@ -0,0 +1,399 @@
+use std::{collections::HashMap, io::Write, rc::Rc};
+
+use crate::{
+    builtin::Builtin,
+    diagnostic::{Diagnostic, RUNTIME_ERROR},
+    expr::{Expr, LiteralValue},
+    stmt::Stmt,
+    token::{Span, Token},
+    tokentype::TokenType,
+};
+
+struct Function {
+    params: Vec<(Token, TokenType)>,
+    return_type: TokenType,
+    body: Vec<Stmt>,
+}
+
+// how a statement finished, loops and calls stop unwinding at the matching variant
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(LiteralValue),
+}
+
+/// Evaluates a checked program directly from its AST, printing to `out`.
+///
+/// It follows the semantics of the LLVM backend, including its output format,
+/// so both can run the same programs and be compared against each other.
+pub struct Interpreter<W: Write> {
+    scopes: Vec<HashMap<String, LiteralValue>>,
+    functions: HashMap<String, Rc<Function>>,
+    out: W,
+}
+
+impl<W: Write> Interpreter<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            scopes: vec![HashMap::new()],
+            functions: HashMap::new(),
+            out,
+        }
+    }
+
+    pub fn interpret(&mut self, statements: &[Stmt]) -> Result<(), Diagnostic> {
+        // top level functions can be called before their definition
+        for statement in statements {
+            self.define_function(statement);
+        }
+        for statement in statements {
+            self.execute(statement)?;
+        }
+        let _ = self.out.flush();
+        Ok(())
+    }
+
+    fn execute(&mut self, st: &Stmt) -> Result<Flow, Diagnostic> {
+        match st {
+            Stmt::Var {
+                name,
+                data_type,
+                initializer,
+                ..
+            } => {
+                let value = match initializer {
+                    Expr::Literal {
+                        value: LiteralValue::Nil,
+                        ..
+                    } => zero_value(*data_type),
+                    initializer => self.evaluate(initializer)?,
+                };
+                self.scopes
+                    .last_mut()
+                    .unwrap()
+                    .insert(name.lexeme.clone(), value);
+            }
+            Stmt::Expression { expression, .. } => {
+                self.evaluate(expression)?;
+            }
+            Stmt::Print { expression, .. } => {
+                let value = self.evaluate(expression)?;
+                let _ = writeln!(self.out, "{}", format_value(&value));
+            }
+            Stmt::Block { stmts, .. } => {
+                self.scopes.push(HashMap::new());
+                let flow = self.execute_block(stmts);
+                self.scopes.pop();
+                return flow;
+            }
+            Stmt::IfElse {
+                condition,
+                then,
+                els,
+                ..
+            } => {
+                if self.evaluate(condition)?.is_truthy() {
+                    return self.execute(then);
+                } else if let Some(els) = els {
+                    return self.execute(els);
+                }
+            }
+            Stmt::WHILE {
+                condition,
+                block,
+                increment,
+                ..
+            } => {
+                while self.evaluate(condition)?.is_truthy() {
+                    match self.execute(block)? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Normal | Flow::Continue => (),
+                    }
+                    if let Some(increment) = increment {
+                        self.evaluate(increment)?;
+                    }
+                }
+            }
+            Stmt::Function { .. } => self.define_function(st),
+            Stmt::Return { expr, .. } => {
+                let value = match expr {
+                    Some(expr) => self.evaluate(expr)?,
+                    None => LiteralValue::Nil,
+                };
+                return Ok(Flow::Return(value));
+            }
+            Stmt::Break { .. } => return Ok(Flow::Break),
+            Stmt::Continue { .. } => return Ok(Flow::Continue),
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn execute_block(&mut self, statements: &[Stmt]) -> Result<Flow, Diagnostic> {
+        for statement in statements {
+            match self.execute(statement)? {
+                Flow::Normal => (),
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn define_function(&mut self, st: &Stmt) {
+        if let Stmt::Function {
+            name,
+            params,
+            return_type,
+            body,
+            ..
+        } = st
+        {
+            let function = Function {
+                params: params.clone(),
+                return_type: *return_type,
+                body: body.clone(),
+            };
+            self.functions.insert(name.lexeme.clone(), Rc::new(function));
+        }
+    }
+
+    fn evaluate(&mut self, expr: &Expr) -> Result<LiteralValue, Diagnostic> {
+        Ok(match expr {
+            Expr::Literal { value, .. } => value.clone(),
+            Expr::Grouping { expression, .. } => self.evaluate(expression)?,
+            Expr::Variable { name, .. } => match self.lookup(name) {
+                Some(value) => value.clone(),
+                None => return Err(error(name.span, format!("undefined variable '{}'", name.lexeme))),
+            },
+            Expr::Assign { name, value, .. } => {
+                let value = self.evaluate(value)?;
+                match self.lookup(name) {
+                    Some(slot) => *slot = value.clone(),
+                    None => {
+                        return Err(error(
+                            name.span,
+                            format!("undefined variable '{}'", name.lexeme),
+                        ));
+                    }
+                }
+                value
+            }
+            Expr::Unary {
+                operator, right, ..
+            } => {
+                let right = self.evaluate(right)?;
+                match (operator.token_type, right) {
+                    (TokenType::MINUS, LiteralValue::Int(n)) => LiteralValue::Int(n.wrapping_neg()),
+                    (TokenType::MINUS, LiteralValue::Number(n)) => LiteralValue::Number(-n),
+                    (TokenType::BANG, LiteralValue::True) => LiteralValue::False,
+                    (TokenType::BANG, LiteralValue::False) => LiteralValue::True,
+                    (_, value) => {
+                        return Err(error(
+                            operator.span,
+                            format!("cannot apply '{}' to {}", operator.lexeme, value.to_type()),
+                        ));
+                    }
+                }
+            }
+            Expr::Interpolated { parts, .. } => {
+                let mut string = String::new();
+                for part in parts {
+                    string.push_str(&format_value(&self.evaluate(part)?));
+                }
+                LiteralValue::StringValue(string)
+            }
+            Expr::Cast {
+                expression, target, ..
+            } => {
+                let value = self.evaluate(expression)?;
+                match (value, target.token_type) {
+                    (LiteralValue::Int(n), TokenType::FLOAT) => LiteralValue::Number(n as f64),
+                    // saturates and turns nan into 0, like llvm.fptosi.sat
+                    (LiteralValue::Number(x), TokenType::INT) => LiteralValue::Int(x as i64),
+                    (LiteralValue::True, TokenType::INT) => LiteralValue::Int(1),
+                    (LiteralValue::False, TokenType::INT) => LiteralValue::Int(0),
+                    (value, TokenType::STRING) => LiteralValue::StringValue(format_value(&value)),
+                    (value, _) => value,
+                }
+            }
+            Expr::Logical {
+                expression,
+                operator,
+                right,
+                ..
+            } => {
+                let left = self.evaluate(expression)?.is_truthy();
+                // the right side only runs when the left doesn't decide the result
+                let result = match operator.token_type {
+                    TokenType::AND => left && self.evaluate(right)?.is_truthy(),
+                    _ => left || self.evaluate(right)?.is_truthy(),
+                };
+                LiteralValue::from_bool(result)
+            }
+            Expr::Binary {
+                left,
+                operator,
+                right,
+                ..
+            } => {
+                let left = self.evaluate(left)?;
+                let right = self.evaluate(right)?;
+                binary(operator, left, right)?
+            }
+            Expr::Call { callie, args, .. } => {
+                let Expr::Variable { name, .. } = callie.as_ref() else {
+                    return Err(error(callie.span(), "can only call functions"));
+                };
+                let Some(function) = self.functions.get(&name.lexeme).cloned() else {
+                    if let Some(builtin) = Builtin::lookup(&name.lexeme) {
+                        let mut values = vec![];
+                        for arg in args {
+                            values.push(self.evaluate(arg)?);
+                        }
+                        return call_builtin(builtin, name, values);
+                    }
+                    return Err(error(name.span, format!("undefined function '{}'", name.lexeme)));
+                };
+                let mut scope = HashMap::new();
+                for ((param, _), arg) in function.params.iter().zip(args) {
+                    scope.insert(param.lexeme.clone(), self.evaluate(arg)?);
+                }
+                self.call(&function, scope)?
+            }
+        })
+    }
+
+    fn call(
+        &mut self,
+        function: &Function,
+        scope: HashMap<String, LiteralValue>,
+    ) -> Result<LiteralValue, Diagnostic> {
+        // functions only see their own parameters and locals
+        let outer_scopes = std::mem::replace(&mut self.scopes, vec![scope]);
+        let flow = self.execute_block(&function.body);
+        self.scopes = outer_scopes;
+        Ok(match flow? {
+            Flow::Return(value) => value,
+            // falling off the end returns the zero value of the return type
+            _ => zero_value(function.return_type),
+        })
+    }
+
+    fn lookup(&mut self, name: &Token) -> Option<&mut LiteralValue> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(&name.lexeme))
+    }
+}
+
+fn binary(
+    operator: &Token,
+    left: LiteralValue,
+    right: LiteralValue,
+) -> Result<LiteralValue, Diagnostic> {
+    use LiteralValue::{Int, Number, StringValue};
+
+    Ok(match (left, operator.token_type, right) {
+        // ints wrap around like the i64 arithmetic LLVM generates
+        (Int(a), TokenType::PLUS, Int(b)) => Int(a.wrapping_add(b)),
+        (Int(a), TokenType::MINUS, Int(b)) => Int(a.wrapping_sub(b)),
+        (Int(a), TokenType::STAR, Int(b)) => Int(a.wrapping_mul(b)),
+        (Int(_), TokenType::SLASH | TokenType::Modulus, Int(0)) => {
+            return Err(error(operator.span, "division by zero"));
+        }
+        (Int(a), TokenType::SLASH, Int(b)) => Int(a.wrapping_div(b)),
+        (Int(a), TokenType::Modulus, Int(b)) => Int(a.wrapping_rem(b)),
+        (Int(a), TokenType::GREATER, Int(b)) => LiteralValue::from_bool(a > b),
+        (Int(a), TokenType::GREATER_EQUAL, Int(b)) => LiteralValue::from_bool(a >= b),
+        (Int(a), TokenType::LESS, Int(b)) => LiteralValue::from_bool(a < b),
+        (Int(a), TokenType::LESS_EQUAL, Int(b)) => LiteralValue::from_bool(a <= b),
+        (Number(a), TokenType::PLUS, Number(b)) => Number(a + b),
+        (Number(a), TokenType::MINUS, Number(b)) => Number(a - b),
+        (Number(a), TokenType::STAR, Number(b)) => Number(a * b),
+        (Number(a), TokenType::SLASH, Number(b)) => Number(a / b),
+        (Number(a), TokenType::Modulus, Number(b)) => Number(a % b),
+        (Number(a), TokenType::GREATER, Number(b)) => LiteralValue::from_bool(a > b),
+        (Number(a), TokenType::GREATER_EQUAL, Number(b)) => LiteralValue::from_bool(a >= b),
+        (Number(a), TokenType::LESS, Number(b)) => LiteralValue::from_bool(a < b),
+        (Number(a), TokenType::LESS_EQUAL, Number(b)) => LiteralValue::from_bool(a <= b),
+        (StringValue(a), TokenType::PLUS, StringValue(b)) => StringValue(a + &b),
+        // byte order, the same the compiled string comparison uses
+        (StringValue(a), TokenType::GREATER, StringValue(b)) => LiteralValue::from_bool(a > b),
+        (StringValue(a), TokenType::GREATER_EQUAL, StringValue(b)) => {
+            LiteralValue::from_bool(a >= b)
+        }
+        (StringValue(a), TokenType::LESS, StringValue(b)) => LiteralValue::from_bool(a < b),
+        (StringValue(a), TokenType::LESS_EQUAL, StringValue(b)) => LiteralValue::from_bool(a <= b),
+        (a, TokenType::EQUAL_EQUAL, b) => LiteralValue::from_bool(a == b),
+        (a, TokenType::BANG_EQUAL, b) => LiteralValue::from_bool(a != b),
+        (a, _, b) => {
+            return Err(error(
+                operator.span,
+                format!(
+                    "cannot apply '{}' to {} and {}",
+                    operator.lexeme,
+                    a.to_type(),
+                    b.to_type()
+                ),
+            ));
+        }
+    })
+}
+
+fn call_builtin(
+    builtin: Builtin,
+    name: &Token,
+    args: Vec<LiteralValue>,
+) -> Result<LiteralValue, Diagnostic> {
+    use LiteralValue::{Int, StringValue};
+
+    Ok(match (builtin, args.as_slice()) {
+        (Builtin::Len, [StringValue(s)]) => Int(s.len() as i64),
+        (Builtin::Substring, [StringValue(s), Int(start), Int(end)]) => {
+            if *start < 0 || end < start || *end > s.len() as i64 {
+                return Err(error(name.span, "substring out of range"));
+            }
+            // lengths count bytes, but a cut may not split a character
+            let (start, end) = (*start as usize, *end as usize);
+            if !s.is_char_boundary(start) || !s.is_char_boundary(end) {
+                return Err(error(name.span, "substring cuts a character in half"));
+            }
+            StringValue(s[start..end].to_string())
+        }
+        (_, args) => {
+            return Err(error(
+                name.span,
+                format!("cannot call '{}' with {:?}", name.lexeme, args),
+            ));
+        }
+    })
+}
+
+// the value a declaration without initializer starts out with
+fn zero_value(ty: TokenType) -> LiteralValue {
+    match ty {
+        TokenType::INT => LiteralValue::Int(0),
+        TokenType::FLOAT => LiteralValue::Number(0.0),
+        TokenType::BOOL => LiteralValue::False,
+        TokenType::STRING => LiteralValue::StringValue(String::new()),
+        _ => LiteralValue::Nil,
+    }
+}
+
+// formats values the way the compiled program's printf calls do
+fn format_value(value: &LiteralValue) -> String {
+    match value {
+        LiteralValue::Number(n) if n.is_nan() => {
+            let sign = if n.is_sign_negative() { "-" } else { "" };
+            format!("{}nan", sign)
+        }
+        LiteralValue::Number(n) => format!("{:.6}", n),
+        value => value.to_string(),
+    }
+}
+
+fn error(span: Span, msg: impl Into<String>) -> Diagnostic {
+    Diagnostic::error(span, msg).with_code(RUNTIME_ERROR)
+}
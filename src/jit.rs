@@ -0,0 +1,72 @@
+use std::ffi::{c_char, c_int};
+
+use inkwell::{
+    OptimizationLevel,
+    execution_engine::ExecutionEngine,
+    module::Module,
+    targets::{InitializationConfig, Target},
+};
+
+unsafe extern "C" {
+    fn printf(format: *const c_char, ...) -> c_int;
+    fn fflush(stream: *mut u8) -> c_int;
+    fn dprintf(fd: c_int, format: *const c_char, ...) -> c_int;
+    fn exit(code: c_int) -> !;
+    fn malloc(size: usize) -> *mut u8;
+    fn memcpy(dest: *mut u8, src: *const u8, n: usize) -> *mut u8;
+    fn memcmp(left: *const u8, right: *const u8, n: usize) -> c_int;
+    fn snprintf(buffer: *mut c_char, size: usize, format: *const c_char, ...) -> c_int;
+    fn fmod(x: f64, y: f64) -> f64;
+}
+
+type MainFn = unsafe extern "C" fn() -> i32;
+
+// Runtime functions the generated code calls, with the address the JIT should bind them to.
+// Mapping them explicitly means we don't depend on the JIT finding them through dlsym.
+fn runtime_symbols() -> Vec<(&'static str, usize)> {
+    vec![
+        ("printf", printf as *const () as usize),
+        ("dprintf", dprintf as *const () as usize),
+        ("exit", exit as *const () as usize),
+        ("malloc", malloc as *const () as usize),
+        ("memcpy", memcpy as *const () as usize),
+        ("memcmp", memcmp as *const () as usize),
+        ("snprintf", snprintf as *const () as usize),
+        ("fmod", fmod as *const () as usize),
+    ]
+}
+
+/// Compiles `module` in memory and calls its `main`, returning the exit code.
+pub fn run(module: &Module, opt_level: OptimizationLevel) -> Result<i32, String> {
+    let engine = create_engine(module, opt_level)?;
+    let main = unsafe { engine.get_function::<MainFn>("main") }.map_err(|e| e.to_string())?;
+    let code = unsafe { main.call() };
+    flush_output();
+    Ok(code)
+}
+
+pub fn create_engine<'ctx>(
+    module: &Module<'ctx>,
+    opt_level: OptimizationLevel,
+) -> Result<ExecutionEngine<'ctx>, String> {
+    Target::initialize_native(&InitializationConfig::default())?;
+    let engine = module
+        .create_jit_execution_engine(opt_level)
+        .map_err(|e| e.to_string())?;
+    map_runtime(&engine, module);
+    Ok(engine)
+}
+
+// binds the runtime functions `module` declares to their addresses in this process
+pub fn map_runtime(engine: &ExecutionEngine, module: &Module) {
+    for (name, address) in runtime_symbols() {
+        if let Some(function) = module.get_function(name) {
+            engine.add_global_mapping(&function, address);
+        }
+    }
+}
+
+// the program printed through C stdio, flush it before Rust writes anything else
+pub fn flush_output() {
+    unsafe { fflush(std::ptr::null_mut()) };
+}
@@ -1,8 +1,11 @@
-use core::panic;
 use std::{any::type_name, collections::HashMap, string, usize};
 
 use crate::{
-    token::{Literal, Token},
+    diagnostic::{
+        Diagnostic, INVALID_ESCAPE, INVALID_NUMBER, SYNTAX_ERROR, UNKNOWN_CHARACTER,
+        UNTERMINATED_COMMENT, UNTERMINATED_STRING,
+    },
+    token::{Literal, Span, StringPart, Token},
     tokentype::TokenType,
 };
 
@@ -12,7 +15,17 @@ pub struct Scanner {
     start: usize,
     current: usize,
     line: usize,
+    // byte offset of the first character on the current line
+    line_start: usize,
+    // position of the token being scanned, strings can span several lines
+    start_line: usize,
+    start_column: usize,
     keywords: HashMap<&'static str, TokenType>,
+    errors: Vec<Diagnostic>,
+    // lines of `///` comments waiting for the token they document
+    doc: Vec<String>,
+    // how many `${...}` the scanner is inside of
+    interpolations: usize,
 }
 
 impl Scanner {
@@ -23,18 +36,37 @@ impl Scanner {
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+            start_line: 1,
+            start_column: 1,
             keywords: Self::init_keywords(),
+            errors: Vec::new(),
+            doc: Vec::new(),
+            interpolations: 0,
         }
     }
 
-    pub fn scanTokens(mut self) -> Vec<Token> {
+    // scans the whole source even after an error, so every lexical error is reported at once
+    pub fn scanTokens(mut self) -> Result<Vec<Token>, Vec<Diagnostic>> {
         while !self.is_at_end() {
             self.start = self.current;
+            self.start_line = self.line;
+            self.start_column = self.column_at(self.current);
             self.scanToken()
         }
+        let eof = Span::new(
+            self.line,
+            self.column_at(self.current),
+            self.current,
+            self.current,
+        );
         self.tokens
-            .push(Token::new(TokenType::EOF, "".to_owned(), None, self.line));
-        self.tokens
+            .push(Token::new(TokenType::EOF, "".to_owned(), None, eof));
+        if self.errors.is_empty() {
+            Ok(self.tokens)
+        } else {
+            Err(self.errors)
+        }
     }
 
     fn scanToken(&mut self) {
@@ -47,7 +79,13 @@ impl Scanner {
             '}' => self.token_add(TokenType::RIGHT_BRACE),
             ',' => self.token_add(TokenType::COMMA),
             '.' => self.token_add(TokenType::DOT),
-            '-' => self.token_add(TokenType::MINUS),
+            '-' => {
+                let token = match self.match_token('>') {
+                    true => TokenType::ARROW,
+                    false => TokenType::MINUS,
+                };
+                self.token_add(token);
+            }
             '+' => self.token_add(TokenType::PLUS),
             ';' => self.token_add(TokenType::SEMICOLON),
             '*' => self.token_add(TokenType::STAR),
@@ -80,22 +118,38 @@ impl Scanner {
                 self.token_add(token);
             }
             '/' => {
-                match self.match_token('/') {
-                    true => {
-                        while self.peek() != '\n' && !self.is_at_end() {
-                            self.advance();
-                        }
-                    }
-                    false => self.token_add(TokenType::SLASH),
-                };
+                if self.interpolations > 0 && matches!(self.peek(), '/' | '*') {
+                    // a comment would run past the `}` and the closing quote
+                    self.advance();
+                    let error = Diagnostic::error(
+                        self.current_span(),
+                        "comments are not allowed inside `${...}`",
+                    )
+                    .with_code(SYNTAX_ERROR)
+                    .with_label("this starts a comment")
+                    .with_help("move the comment out of the string");
+                    self.errors.push(error);
+                } else if self.match_token('/') {
+                    self.line_comment();
+                } else if self.match_token('*') {
+                    self.block_comment();
+                } else {
+                    self.token_add(TokenType::SLASH);
+                }
             }
             ' ' | '\r' | '\t' => {}
-            '\n' => self.line += 1,
+            '\n' => self.new_line(),
             '"' => self.string(),
             c if Self::is_digit(c) => self.number(),
             c if Self::is_alpha(c) => self.identifier(),
-            e => {
-                panic!("Uknown Symbol {}", e);
+            _ => {
+                let symbol = &self.source[self.start..self.current];
+                let error = Diagnostic::error(
+                    self.current_span(),
+                    format!("unknown character '{}'", symbol),
+                )
+                .with_code(UNKNOWN_CHARACTER);
+                self.errors.push(error);
             }
         }
     }
@@ -125,38 +179,277 @@ impl Scanner {
             }
         }
 
-        let value = self.source[self.start..self.current]
-            .parse::<f64>()
-            .unwrap();
+        // `12abc` is one bad literal, not a number followed by an identifier
+        if Self::is_alpha(self.peek()) {
+            while Self::is_alpha_numeric(self.peek()) {
+                self.advance();
+            }
+        }
+
+        let text = &self.source[self.start..self.current];
+        // a literal without a fractional part is an int
+        let literal = if text.contains('.') {
+            text.parse::<f64>().ok().map(Literal::FLiteral)
+        } else {
+            text.parse::<i64>().ok().map(Literal::ILiteral)
+        };
+        match literal {
+            Some(literal) => self.add_token(TokenType::NUMBER, Some(literal)),
+            None => {
+                let label = if text.bytes().all(|b| b.is_ascii_digit()) {
+                    "too large for an int"
+                } else {
+                    "not a valid number"
+                };
+                let error = Diagnostic::error(
+                    self.current_span(),
+                    format!("invalid number literal '{}'", text),
+                )
+                .with_code(INVALID_NUMBER)
+                .with_label(label);
+                self.errors.push(error);
+            }
+        }
+    }
+    // `//` up to the end of the line. Exactly three slashes make a doc comment,
+    // which is kept for the next token, `////` is an ordinary comment again.
+    fn line_comment(&mut self) {
+        let is_doc = self.peek() == '/' && self.peek_next() != '/';
+        while self.peek() != '\n' && !self.is_at_end() {
+            self.advance();
+        }
+        if is_doc {
+            let text = &self.source[self.start + 3..self.current];
+            let text = text.strip_prefix(' ').unwrap_or(text);
+            self.doc.push(text.trim_end().to_owned());
+        }
+    }
 
-        self.add_token(TokenType::NUMBER, Some(Literal::FLiteral(value)));
+    // `/* ... */`, which nests so code that already has comments can be commented out
+    fn block_comment(&mut self) {
+        let mut depth = 1;
+        while depth > 0 {
+            if self.is_at_end() {
+                let opening = Span::new(
+                    self.start_line,
+                    self.start_column,
+                    self.start,
+                    self.start + 2,
+                );
+                let error = Diagnostic::error(opening, "unterminated block comment")
+                    .with_code(UNTERMINATED_COMMENT)
+                    .with_label("comment starts here")
+                    .with_help("add a closing '*/'");
+                self.errors.push(error);
+                return;
+            }
+            match self.advance() {
+                '/' if self.match_token('*') => depth += 1,
+                '*' if self.match_token('/') => depth -= 1,
+                '\n' => self.new_line(),
+                _ => {}
+            }
+        }
     }
+
     fn string(&mut self) {
+        let mut parts = vec![];
+        // the decoded text since the last interpolation
+        let mut text = String::new();
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
+            match self.advance() {
+                '\n' => {
+                    self.new_line();
+                    text.push('\n');
+                }
+                '\\' => {
+                    if let Some(c) = self.escape() {
+                        text.push(c);
+                    }
+                }
+                '$' if self.peek() == '{' => {
+                    self.advance();
+                    parts.push(StringPart::Text(std::mem::take(&mut text)));
+                    parts.push(StringPart::Code(self.interpolation()));
+                }
+                c => text.push(c),
             }
-            self.advance();
         }
         if self.is_at_end() {
-            println!("{} Unterminated String", self.line);
+            // point at the opening quote, the end of the file says little about which string it was
+            let quote = Span::new(
+                self.start_line,
+                self.start_column,
+                self.start,
+                self.start + 1,
+            );
+            let error = Diagnostic::error(quote, "unterminated string")
+                .with_code(UNTERMINATED_STRING)
+                .with_label("string starts here")
+                .with_help("add a closing '\"'");
+            self.errors.push(error);
             return;
         }
         self.advance();
-        let string = self.source[self.start + 1..self.current - 1].to_owned();
-        self.add_token(TokenType::STRINGLIT, Some(Literal::StringLiteral(string)));
+        let literal = if parts.is_empty() {
+            Literal::StringLiteral(text)
+        } else {
+            parts.push(StringPart::Text(text));
+            Literal::Interpolated(parts)
+        };
+        self.add_token(TokenType::STRINGLIT, Some(literal));
     }
 
-    fn peek(&self) -> char {
+    // Decodes the escape sequence whose backslash was just consumed. Invalid
+    // ones are reported and give None, the rest of the string still scans.
+    fn escape(&mut self) -> Option<char> {
+        let backslash = self.current - 1;
         if self.is_at_end() {
-            return '\0';
+            // reported as an unterminated string
+            return None;
         }
-        self.source.as_bytes()[self.current] as char
+        let c = match self.advance() {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '\\' => '\\',
+            '"' => '"',
+            '$' => '$',
+            'u' => return self.unicode_escape(backslash),
+            c @ ('\r' | '\n') => {
+                let span = Span::new(
+                    self.line,
+                    self.column_at(backslash),
+                    backslash,
+                    backslash + 1,
+                );
+                if c == '\n' || self.match_token('\n') {
+                    self.new_line();
+                }
+                self.errors.push(
+                    Diagnostic::error(span, "line continuation is not supported in strings")
+                        .with_code(INVALID_ESCAPE)
+                        .with_label("backslash at the end of the line")
+                        .with_help("remove it to keep the line break in the string"),
+                );
+                return None;
+            }
+            c => {
+                let span = self.span_from(backslash);
+                let label = format!("unknown escape '\\{}'", c.escape_debug());
+                self.errors.push(
+                    Diagnostic::error(span, "invalid escape sequence")
+                        .with_code(INVALID_ESCAPE)
+                        .with_label(label)
+                        .with_help("the escapes are \\n, \\t, \\r, \\\\, \\\", \\$ and \\u{...}"),
+                );
+                return None;
+            }
+        };
+        Some(c)
+    }
+
+    // `\u{1F600}`: one to six hex digits naming a unicode scalar value
+    fn unicode_escape(&mut self, backslash: usize) -> Option<char> {
+        let error = |scanner: &Self, label: &str| {
+            Diagnostic::error(scanner.span_from(backslash), "invalid unicode escape")
+                .with_code(INVALID_ESCAPE)
+                .with_label(label)
+        };
+        if !self.match_token('{') {
+            let error = error(self, "expected '{' after '\\u'").with_help("write it as \\u{1F600}");
+            self.errors.push(error);
+            return None;
+        }
+        let digits_start = self.current;
+        while self.peek().is_ascii_hexdigit() {
+            self.advance();
+        }
+        let digits = &self.source[digits_start..self.current];
+        let code_point = u32::from_str_radix(digits, 16)
+            .ok()
+            .filter(|_| digits.len() <= 6);
+        if !self.match_token('}') {
+            let error = error(self, "expected hex digits and a closing '}'");
+            self.errors.push(error);
+            return None;
+        }
+        match code_point.map(char::from_u32) {
+            // strings are handed to C, where a NUL would cut them short
+            Some(Some('\0')) => {
+                let error = error(self, "strings can't contain NUL characters");
+                self.errors.push(error);
+                None
+            }
+            Some(Some(c)) => Some(c),
+            Some(None) => {
+                let error = error(self, "not a unicode scalar value");
+                self.errors.push(error);
+                None
+            }
+            None => {
+                let error = error(self, "expected 1 to 6 hex digits");
+                self.errors.push(error);
+                None
+            }
+        }
+    }
+
+    // Scans the expression of a `${...}` whose `${` was just consumed, up to
+    // and including the `}` that closes it, and returns its tokens.
+    fn interpolation(&mut self) -> Vec<Token> {
+        // the string's own token is added once its closing quote is found
+        let string_start = (self.start, self.start_line, self.start_column);
+        let outer_tokens = std::mem::take(&mut self.tokens);
+        self.interpolations += 1;
+        let mut depth = 0;
+        loop {
+            self.start = self.current;
+            self.start_line = self.line;
+            self.start_column = self.column_at(self.current);
+            if self.is_at_end() || (self.peek() == '}' && depth == 0) {
+                break;
+            }
+            let scanned = self.tokens.len();
+            self.scanToken();
+            match self.tokens.get(scanned).map(|token| token.token_type) {
+                Some(TokenType::LEFT_BRACE) => depth += 1,
+                Some(TokenType::RIGHT_BRACE) => depth -= 1,
+                _ => (),
+            }
+        }
+        let end = Span::new(
+            self.start_line,
+            self.start_column,
+            self.current,
+            self.current,
+        );
+        // the `}` ends the expression the way EOF ends a file
+        let lexeme = if self.is_at_end() { "" } else { "}" };
+        self.tokens
+            .push(Token::new(TokenType::EOF, lexeme.to_owned(), None, end));
+        if !self.is_at_end() {
+            self.advance();
+        }
+
+        self.interpolations -= 1;
+        (self.start, self.start_line, self.start_column) = string_start;
+        std::mem::replace(&mut self.tokens, outer_tokens)
+    }
+
+    // called right after consuming a '\n'
+    fn new_line(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
+    }
+
+    fn peek(&self) -> char {
+        self.source[self.current..].chars().next().unwrap_or('\0')
     }
     fn advance(&mut self) -> char {
-        let char = self.source.as_bytes()[self.current as usize];
-        self.current += 1;
-        char as char
+        let c = self.peek();
+        self.current += c.len_utf8();
+        c
     }
 
     fn is_at_end(&self) -> bool {
@@ -164,21 +457,16 @@ impl Scanner {
     }
 
     fn peek_next(&self) -> char {
-        if self.current + 1 >= self.source.len() {
-            '\0'
-        } else {
-            self.source
-                .chars()
-                .nth((self.current + 1) as usize)
-                .unwrap()
-        }
+        let mut chars = self.source[self.current..].chars();
+        chars.next();
+        chars.next().unwrap_or('\0')
     }
 
     fn match_token(&mut self, expected: char) -> bool {
-        if self.is_at_end() || self.source.as_bytes()[self.current] as char != expected {
+        if self.is_at_end() || self.peek() != expected {
             return false;
         }
-        self.current += 1;
+        self.advance();
         true
     }
 
@@ -188,13 +476,33 @@ impl Scanner {
 
     fn add_token(&mut self, type_token: TokenType, literal: Option<Literal>) {
         let text = self.source[self.start as usize..self.current as usize].to_string();
-        self.tokens
-            .push(Token::new(type_token, text, literal, self.line));
+        let span = self.current_span();
+        let mut token = Token::new(type_token, text, literal, span);
+        if !self.doc.is_empty() {
+            token.doc = Some(std::mem::take(&mut self.doc).join("\n"));
+        }
+        self.tokens.push(token);
+    }
+
+    // span of the token scanned so far
+    fn current_span(&self) -> Span {
+        Span::new(self.start_line, self.start_column, self.start, self.current)
+    }
+
+    // span from byte `start` on the current line up to the current position
+    fn span_from(&self, start: usize) -> Span {
+        Span::new(self.line, self.column_at(start), start, self.current)
+    }
+
+    // columns count characters, not bytes
+    fn column_at(&self, offset: usize) -> usize {
+        self.source[self.line_start..offset].chars().count() + 1
     }
     fn init_keywords() -> HashMap<&'static str, TokenType> {
         let mut keywords = HashMap::new();
 
         keywords.insert("and", TokenType::AND);
+        keywords.insert("as", TokenType::AS);
         keywords.insert("class", TokenType::CLASS);
         keywords.insert("else", TokenType::ELSE);
         keywords.insert("false", TokenType::FALSE);
@@ -208,6 +516,7 @@ impl Scanner {
         keywords.insert("super", TokenType::SUPER);
         keywords.insert("this", TokenType::THIS);
         keywords.insert("true", TokenType::TRUE);
+        keywords.insert("int", TokenType::INT);
         keywords.insert("float", TokenType::FLOAT);
         keywords.insert("string", TokenType::STRING);
         keywords.insert("bool", TokenType::BOOL);
@@ -217,8 +526,9 @@ impl Scanner {
         keywords
     }
 
+    // identifiers start with a letter or '_', so `arr_ok` and `_tmp` are names
     fn is_alpha(c: char) -> bool {
-        c >= 'a' && c <= 'z' || c >= 'A' && c <= 'Z'
+        c >= 'a' && c <= 'z' || c >= 'A' && c <= 'Z' || c == '_'
     }
 
     fn is_alpha_numeric(c: char) -> bool {
@@ -0,0 +1,126 @@
+use std::{
+    env::{self, consts::ARCH},
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+// C compiler drivers know where the crt objects and libc live, so they are tried first
+const DRIVERS: [&str; 3] = ["cc", "clang", "gcc"];
+
+// where distributions put crt1.o and friends when we have to call `ld`
+// ourselves, after the multiarch directory for this machine
+const LIB_DIRS: [&str; 3] = ["/usr/lib64", "/usr/lib", "/lib64"];
+// the dynamic loader of each architecture we know how to link for
+const DYNAMIC_LINKERS: [(&str, &str); 2] = [
+    ("x86_64", "/lib64/ld-linux-x86-64.so.2"),
+    ("aarch64", "/lib/ld-linux-aarch64.so.1"),
+];
+// gcc keeps crtbegin.o and crtend.o in /usr/lib/gcc/<triple>/<version>
+const GCC_LIB_DIR: &str = "/usr/lib/gcc";
+
+#[derive(Debug)]
+pub enum LinkError {
+    // neither a C compiler driver nor a usable `ld` setup was found
+    NoLinker,
+    // the linker ran but exited with an error, it has already printed why
+    Failed(String),
+}
+
+impl Display for LinkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LinkError::NoLinker => write!(
+                f,
+                "no linker found, install cc, clang or gcc or point $CC at a C compiler"
+            ),
+            LinkError::Failed(linker) => write!(f, "linking with '{}' failed", linker),
+        }
+    }
+}
+
+/// Links `object` against libc and libm into the executable `output`.
+///
+/// Uses `$CC` if set, then the first of cc, clang and gcc on the path, and
+/// falls back to invoking `ld` directly with the system's crt objects.
+pub fn link(object: &Path, output: &Path) -> Result<(), LinkError> {
+    let drivers = env::var("CC").into_iter().chain(DRIVERS.map(String::from));
+    for driver in drivers {
+        let status = Command::new(&driver)
+            .arg(object)
+            .arg("-o")
+            .arg(output)
+            // fmod, which float `%` calls, lives in libm
+            .arg("-lm")
+            .status();
+        match status {
+            Ok(status) if status.success() => return Ok(()),
+            Ok(_) => return Err(LinkError::Failed(driver)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(_) => return Err(LinkError::Failed(driver)),
+        }
+    }
+    link_with_ld(object, output)
+}
+
+fn link_with_ld(object: &Path, output: &Path) -> Result<(), LinkError> {
+    let multiarch = PathBuf::from(format!("/usr/lib/{}-linux-gnu", ARCH));
+    let (Some(lib_dir), Some((_, dynamic_linker)), Some(gcc_dir)) = (
+        std::iter::once(multiarch)
+            .chain(LIB_DIRS.iter().map(PathBuf::from))
+            .find(|dir| dir.join("crt1.o").exists()),
+        DYNAMIC_LINKERS
+            .iter()
+            .find(|(arch, path)| *arch == ARCH && Path::new(path).exists()),
+        gcc_crt_dir(),
+    ) else {
+        return Err(LinkError::NoLinker);
+    };
+
+    let status = Command::new("ld")
+        .arg("-dynamic-linker")
+        .arg(dynamic_linker)
+        .arg(lib_dir.join("crt1.o"))
+        .arg(lib_dir.join("crti.o"))
+        .arg(gcc_dir.join("crtbegin.o"))
+        .arg(object)
+        .arg("-L")
+        .arg(&lib_dir)
+        .arg("-lm")
+        .arg("-lc")
+        .arg(gcc_dir.join("crtend.o"))
+        .arg(lib_dir.join("crtn.o"))
+        .arg("-o")
+        .arg(output)
+        .status();
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(_) => Err(LinkError::Failed("ld".to_string())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Err(LinkError::NoLinker),
+        Err(_) => Err(LinkError::Failed("ld".to_string())),
+    }
+}
+
+// the newest gcc directory for this architecture that has crtbegin.o
+fn gcc_crt_dir() -> Option<PathBuf> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(GCC_LIB_DIR)
+        .ok()?
+        .flatten()
+        .filter(|triple| triple.file_name().to_string_lossy().starts_with(ARCH))
+        .filter_map(|triple| fs::read_dir(triple.path()).ok())
+        .flatten()
+        .flatten()
+        .map(|version| version.path())
+        .filter(|dir| dir.join("crtbegin.o").exists())
+        .collect();
+    // compare versions numerically, so 12 comes after 9
+    dirs.sort_by_key(|dir| {
+        let version = dir.file_name().unwrap_or_default().to_string_lossy();
+        version
+            .split('.')
+            .map(|part| part.parse::<u32>().unwrap_or(0))
+            .collect::<Vec<_>>()
+    });
+    dirs.pop()
+}
@@ -1,82 +1,324 @@
+use std::{env, fs::read_to_string, io::stdout, process::exit};
+#[cfg(feature = "llvm")]
 use std::{
-    env,
-    fs::read_to_string,
-    io::{BufRead, Write, stdin, stdout},
-    path::Path,
-    process::exit,
+    fs::{DirBuilder, remove_dir_all, write},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+    time::SystemTime,
 };
 
-use inkwell::{context::Context, targets::InitializationConfig};
+#[cfg(feature = "llvm")]
+use crate::cli::Emit;
+#[cfg(feature = "llvm")]
+use inkwell::{
+    OptimizationLevel,
+    context::Context,
+    module::Module,
+    passes::PassBuilderOptions,
+    targets::{FileType, InitializationConfig, Target, TargetMachine},
+};
 
-use crate::{lexer::Scanner, parser::Parser, token::Token};
+use crate::{
+    checker::Checker,
+    cli::{Backend, CliError, Command, Options},
+    diagnostic::Diagnostic,
+    interpreter::Interpreter,
+    lexer::Scanner,
+    parser::Parser,
+    stmt::Stmt,
+    token::Token,
+};
 
+mod builtin;
+mod checker;
+mod cli;
+#[cfg(feature = "llvm")]
 mod codegen;
+mod diagnostic;
 mod expr;
+mod interpreter;
+#[cfg(feature = "llvm")]
+mod jit;
 mod lexer;
+#[cfg(feature = "llvm")]
+mod link;
 mod parser;
+#[cfg(feature = "llvm")]
+mod repl;
+#[cfg(feature = "llvm")]
+mod runtime;
 mod stmt;
 mod token;
 mod tokentype;
 
-#[allow(warnings)]
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    println!("{:?}", args);
-
-    if args.len() > 2 {
-        println!("Using : jlox [script]");
-        exit(64);
-    } else if args.len() == 2 {
-        let path = args.get(1).unwrap();
-        execute_file(&path);
-    } else {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() {
+        #[cfg(feature = "llvm")]
+        exit(repl::run());
+        #[cfg(not(feature = "llvm"))]
+        {
+            eprintln!("error: {}", WITHOUT_LLVM);
+            exit(cli::EXIT_UNAVAILABLE);
+        }
+    }
+    let options = match cli::parse_args(&args) {
+        Ok(options) => options,
+        Err(CliError::Help) => {
+            println!("{}", cli::USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(cli::EXIT_USAGE);
+        }
+    };
+    exit(execute_file(&options));
+}
+
+#[cfg(not(feature = "llvm"))]
+const WITHOUT_LLVM: &str =
+    "testlang was built without LLVM, only `check` and `run --backend=interp` are available";
+
+// Runs the command in `options` and returns the process exit code.
+fn execute_file(options: &Options) -> i32 {
+    let path = options.input.display().to_string();
+    let data = match read_to_string(&options.input) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("error: cannot read '{}': {}", path, e);
+            return cli::EXIT_NO_INPUT;
+        }
+    };
+
+    let mut diagnostics = vec![];
+    let statements = run(data.clone(), &mut diagnostics);
+    for diagnostic in &diagnostics {
+        eprintln!("{}", diagnostic.render(&path, &data));
+    }
+    let Some(statements) = statements else {
+        return cli::EXIT_COMPILE_ERROR;
+    };
+
+    match options.command {
+        // `check` stops after the front end
+        Command::Check => return 0,
+        Command::Run if options.backend == Backend::Interp => {
+            let mut interpreter = Interpreter::new(stdout().lock());
+            return match interpreter.interpret(&statements) {
+                Ok(()) => 0,
+                Err(error) => {
+                    eprintln!("{}", error.render(&path, &data));
+                    cli::EXIT_INTERNAL
+                }
+            };
+        }
+        _ => (),
+    }
+
+    #[cfg(feature = "llvm")]
+    return execute_llvm(options, statements, &path, &data);
+    #[cfg(not(feature = "llvm"))]
+    {
+        eprintln!("error: {}", WITHOUT_LLVM);
+        cli::EXIT_UNAVAILABLE
     }
 }
-fn execute_file(path: &String) {
-    let data = read_to_string(path).unwrap();
-    match run(data) {
-        Ok(_) => (),
-        Err(e) => println!("{}", e),
+
+// Compiles a checked program with LLVM and builds, emits or runs it.
+#[cfg(feature = "llvm")]
+fn execute_llvm(options: &Options, statements: Vec<Stmt>, path: &str, data: &str) -> i32 {
+    let opt_level = options.opt_level.into();
+    let context = Context::create();
+    let machine = target_machine(opt_level);
+    let module = match compile(&context, statements, &machine, opt_level) {
+        Ok(module) => module,
+        Err(error) => {
+            eprintln!("{}", error.render(path, data));
+            return cli::EXIT_COMPILE_ERROR;
+        }
+    };
+    match options.command {
+        Command::Build => emit(&module, &machine, options.emit, Some(&options.output_path())),
+        Command::Emit => emit(&module, &machine, options.emit, options.output.as_deref()),
+        Command::Run => match jit::run(&module, opt_level) {
+            Ok(code) => code,
+            Err(e) => {
+                eprintln!("error: failed to run '{}': {}", path, e);
+                cli::EXIT_INTERNAL
+            }
+        },
+        Command::Check => unreachable!(),
     }
 }
 
-fn run(bytes: String) -> Result<(), String> {
+// Scans, parses and type checks `bytes`. Warnings and errors are pushed onto
+// `diagnostics`; the program is only returned when there are no errors.
+fn run(bytes: String, diagnostics: &mut Vec<Diagnostic>) -> Option<Vec<Stmt>> {
     let scanner: Scanner = Scanner::new(bytes);
 
-    let tokens: Vec<Token> = scanner.scanTokens();
+    let tokens: Vec<Token> = match scanner.scanTokens() {
+        Ok(tokens) => tokens,
+        Err(errors) => {
+            diagnostics.extend(errors);
+            return None;
+        }
+    };
 
     let mut parser = Parser::new(tokens);
-    let statements = parser.parse()?;
+    let mut statements = match parser.parse() {
+        Ok(statements) => statements,
+        Err(errors) => {
+            diagnostics.extend(errors);
+            return None;
+        }
+    };
 
-    inkwell::targets::Target::initialize_all(&InitializationConfig::default());
+    // report every type error up front instead of panicking halfway through codegen
+    diagnostics.extend(Checker::new().check(&mut statements));
+    if diagnostics.iter().any(Diagnostic::is_error) {
+        return None;
+    }
+    Some(statements)
+}
+
+#[cfg(feature = "llvm")]
+fn target_machine(opt_level: OptimizationLevel) -> TargetMachine {
+    Target::initialize_all(&InitializationConfig::default());
 
-    let target_triple = inkwell::targets::TargetMachine::get_default_triple();
-    let target = inkwell::targets::Target::from_triple(&target_triple)
-        .expect("Could not create target from triple");
+    let target_triple = TargetMachine::get_default_triple();
+    let target =
+        Target::from_triple(&target_triple).expect("Could not create target from triple");
 
-    let target_machine = target
+    target
         .create_target_machine(
             &target_triple,
             "generic", // CPU type
             "",        // features
-            inkwell::OptimizationLevel::Default,
+            opt_level,
             inkwell::targets::RelocMode::PIC,
             inkwell::targets::CodeModel::Default,
         )
-        .expect("Unable to create target machine");
+        .expect("Unable to create target machine")
+}
 
-    let context = Context::create();
-    let mut codegen = codegen::Compiler::new(&context, "tasm");
-
-    codegen.generate(statements);
-    //println!("{}", codegen.module.print_to_string().to_string());
-    target_machine
-        .write_to_file(
-            &codegen.module,
-            inkwell::targets::FileType::Object,
-            Path::new("output.o"),
-        )
-        .expect("Failed to write object file");
-    //println!("{:#?}",tokens);
-    Ok(())
+// Lowers a checked program to a module for `machine`, optimized at `opt_level`.
+#[cfg(feature = "llvm")]
+fn compile<'ctx>(
+    context: &'ctx Context,
+    statements: Vec<Stmt>,
+    machine: &TargetMachine,
+    opt_level: OptimizationLevel,
+) -> Result<Module<'ctx>, Diagnostic> {
+    let mut codegen = codegen::Compiler::new(context, "tasm");
+    codegen.generate(statements)?;
+
+    let module = codegen.module;
+    module.set_triple(&machine.get_triple());
+    module.set_data_layout(&machine.get_target_data().get_data_layout());
+    if opt_level != OptimizationLevel::None {
+        let passes = format!("default<O{}>", opt_level as u32);
+        module
+            .run_passes(&passes, machine, PassBuilderOptions::create())
+            .expect("Failed to run optimization passes");
+    }
+    Ok(module)
+}
+
+// Writes `module` as `kind` to `path`, or to stdout when there is no path.
+#[cfg(feature = "llvm")]
+fn emit(module: &Module, machine: &TargetMachine, kind: Emit, path: Option<&Path>) -> i32 {
+    if kind == Emit::Executable {
+        let output = path.expect("executables are always written to a file");
+        return emit_executable(module, machine, output);
+    }
+
+    let bytes = match kind {
+        Emit::LlvmIr => module.print_to_string().to_string().into_bytes(),
+        Emit::Bitcode => module.write_bitcode_to_memory().as_slice().to_vec(),
+        Emit::Object | Emit::Assembly => {
+            let file_type = match kind {
+                Emit::Object => FileType::Object,
+                _ => FileType::Assembly,
+            };
+            match machine.write_to_memory_buffer(module, file_type) {
+                Ok(buffer) => buffer.as_slice().to_vec(),
+                Err(e) => {
+                    eprintln!("error: failed to generate code: {}", e);
+                    return cli::EXIT_INTERNAL;
+                }
+            }
+        }
+        Emit::Executable => unreachable!(),
+    };
+
+    let result = match path {
+        Some(path) => write(path, &bytes),
+        None => stdout().write_all(&bytes),
+    };
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            let target = path.map_or("stdout".to_string(), |p| p.display().to_string());
+            eprintln!("error: cannot write '{}': {}", target, e);
+            cli::EXIT_IO_ERROR
+        }
+    }
+}
+
+// Writes an object file into a private temporary directory and links it into `output`.
+#[cfg(feature = "llvm")]
+fn emit_executable(module: &Module, machine: &TargetMachine, output: &Path) -> i32 {
+    let dir = match private_temp_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("error: cannot create a temporary directory: {}", e);
+            return cli::EXIT_IO_ERROR;
+        }
+    };
+    let object = dir.join("program.o");
+    if let Err(e) = machine.write_to_file(module, FileType::Object, &object) {
+        let _ = remove_dir_all(&dir);
+        eprintln!("error: failed to generate code: {}", e);
+        return cli::EXIT_INTERNAL;
+    }
+
+    let result = link::link(&object, output);
+    let _ = remove_dir_all(&dir);
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            match e {
+                link::LinkError::NoLinker => cli::EXIT_UNAVAILABLE,
+                link::LinkError::Failed(_) => cli::EXIT_INTERNAL,
+            }
+        }
+    }
+}
+
+// Creates a new directory only this user can enter, so nobody else can plant
+// or replace the files written into it.
+#[cfg(feature = "llvm")]
+fn private_temp_dir() -> io::Result<PathBuf> {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |time| time.subsec_nanos());
+    let mut builder = DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    // creating a directory fails when the name is taken, try another one
+    for attempt in 0..16 {
+        let dir = env::temp_dir().join(format!("testlang-{}-{}-{}", process::id(), nanos, attempt));
+        match builder.create(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        "every temporary directory name was taken",
+    ))
 }
@@ -1,15 +1,24 @@
 use std::{env, process::id, result, usize, vec};
 
 use crate::{
+    diagnostic::{Diagnostic, SYNTAX_ERROR},
     expr::{self, Expr, LiteralValue},
     stmt::Stmt,
-    token::{self, Literal, Token},
+    token::{self, Literal, Span, StringPart, Token},
     tokentype::TokenType,
 };
+// keywords that name a type, they start declarations and annotate signatures
+const TYPES: [TokenType; 4] = [
+    TokenType::INT,
+    TokenType::FLOAT,
+    TokenType::STRING,
+    TokenType::BOOL,
+];
+
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
-    is_error: bool,
+    errors: Vec<Diagnostic>,
 }
 
 impl Parser {
@@ -17,78 +26,125 @@ impl Parser {
         Self {
             tokens,
             current: 0,
-            is_error: false,
+            errors: vec![],
         }
     }
 
-    pub fn parse(&mut self) -> Result<Vec<Stmt>, String> {
+    // keeps going after a syntax error so every error in the file is reported in one run
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<Diagnostic>> {
         let mut stmt = vec![];
 
-        while !self.is_at_end() && !self.is_error {
-            match self.declaration() {
-                Ok(s) => stmt.push(s),
-                Err(e) => {
-                    eprintln!("{}", e);
-                    self.is_error = true;
-                }
+        while !self.is_at_end() {
+            // a '}' the top level never opened, syncronize leaves these for an enclosing block
+            if self.match_tokens(&[TokenType::RIGHT_BRACE]) {
+                let token = self.previous();
+                self.errors.push(self.error(&token, "Unmatched '}'"));
+                continue;
             }
+            if let Some(declaration) = self.declaration() {
+                stmt.push(declaration);
+            }
+        }
+        if self.errors.is_empty() {
+            Ok(stmt)
+        } else {
+            Err(std::mem::take(&mut self.errors))
         }
-        Ok(stmt)
     }
 
-    fn declaration(&mut self) -> Result<Stmt, String> {
-        if self.match_tokens(&[TokenType::FLOAT, TokenType::STRING, TokenType::BOOL]) {
-            match self.var_declaration() {
-                Ok(s) => Ok(s),
-                Err(e) => {
-                    self.syncronize();
-                    Err(e)
-                }
-            }
+    // records the error and skips to the next statement when a declaration fails to parse
+    fn declaration(&mut self) -> Option<Stmt> {
+        let start = self.current;
+        let result = if self.match_tokens(&TYPES) {
+            self.var_declaration()
         } else if self.match_tokens(&[TokenType::FUN]) {
             self.funtion_decl("function")
         } else {
             self.statement()
+        };
+        match result {
+            Ok(s) => Some(s),
+            Err(e) => {
+                self.errors.push(e);
+                // always make progress, or a token no statement can start with would loop forever
+                if self.current == start {
+                    self.advance();
+                }
+                self.syncronize();
+                None
+            }
         }
     }
 
-    fn funtion_decl(&mut self, kind: &str) -> Result<Stmt, String> {
-        let token = self.consume(TokenType::IDENTIFIER, "Expected {kind} name")?;
-        self.consume(TokenType::LEFT_PAREN, "Expected  '(' after {kind} name");
+    fn funtion_decl(&mut self, kind: &str) -> Result<Stmt, Diagnostic> {
+        let start = self.previous().span;
+        // the lexer attaches doc comments to the next token, here the 'fun'
+        let doc = self.previous().doc;
+        let token = self.consume(TokenType::IDENTIFIER, &format!("Expected {kind} name"))?;
+        self.consume(
+            TokenType::LEFT_PAREN,
+            &format!("Expected '(' after {kind} name"),
+        )?;
         let mut params = vec![];
         if !self.check(&TokenType::RIGHT_PAREN) {
             loop {
                 if params.len() >= 255 {
-                    return Err("cant have more than 255 params".to_string());
+                    return Err(Diagnostic::error(
+                        self.peek().span,
+                        "cant have more than 255 params",
+                    )
+                    .with_code(SYNTAX_ERROR));
                 }
-                params.push(self.consume(TokenType::IDENTIFIER, "Expected param name")?);
+                let param_type = self.type_annotation("Expected param type")?;
+                let param = self.consume(TokenType::IDENTIFIER, "Expected param name")?;
+                params.push((param, param_type));
                 if !self.match_tokens(&[TokenType::COMMA]) {
                     break;
                 }
             }
         }
         self.consume(TokenType::RIGHT_PAREN, "Expected ')' after params")?;
+        // functions without a '->' annotation don't return a value
+        let return_type = if self.match_tokens(&[TokenType::ARROW]) {
+            self.type_annotation("Expected return type after '->'")?
+        } else {
+            TokenType::NIL
+        };
         self.consume(TokenType::LEFT_BRACE, "Expected '{' before block")?;
-        let Stmt::Block { stmts } = self.block()? else {
-            return Err("Unexpected issue".to_string());
+        let Stmt::Block { stmts, .. } = self.block()? else {
+            return Err(Diagnostic::error(self.previous().span, "Unexpected issue"));
         };
         Ok(Stmt::Function {
             name: token,
             params,
+            return_type,
             body: stmts,
+            doc,
+            span: self.span_from(start),
         })
     }
 
-    fn var_declaration(&mut self) -> Result<Stmt, String> {
+    fn type_annotation(&mut self, msg: &str) -> Result<TokenType, Diagnostic> {
+        if self.match_tokens(&TYPES) {
+            Ok(self.previous().token_type)
+        } else {
+            Err(self.error(&self.peek(), msg))
+        }
+    }
+
+    fn var_declaration(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.previous().span;
         let type_ = self.previous().token_type;
+        let doc = self.previous().doc;
         let token = self.consume(TokenType::IDENTIFIER, "Expected variable name")?;
         let init;
         if self.match_tokens(&[TokenType::EQUAL]) {
-            init = self.expression();
+            init = self.expression()?;
         } else {
-            init = Ok(Expr::Literal {
+            init = Expr::Literal {
                 value: LiteralValue::Nil,
-            });
+                span: token.span,
+            };
         }
 
         self.consume(
@@ -99,10 +155,12 @@ impl Parser {
         Ok(Stmt::Var {
             name: token,
             data_type: type_,
-            initializer: init?,
+            initializer: init,
+            doc,
+            span: self.span_from(start),
         })
     }
-    fn statement(&mut self) -> Result<Stmt, String> {
+    fn statement(&mut self) -> Result<Stmt, Diagnostic> {
         if self.match_tokens(&[TokenType::PRINT]) {
             self.print_stmt()
         } else if self.match_tokens(&[TokenType::CONTINUE]) {
@@ -123,32 +181,45 @@ impl Parser {
             self.expression_stmt()
         }
     }
-    fn return_stmt(&mut self) -> Result<Stmt, String> {
+    fn return_stmt(&mut self) -> Result<Stmt, Diagnostic> {
         let token = self.previous();
         let mut value = None;
         if !self.check(&TokenType::SEMICOLON) {
             value = Some(self.expression()?);
         }
         self.consume(TokenType::SEMICOLON, "Expected ; after break")?;
-        Ok(Stmt::Return { token, expr: value })
+        Ok(Stmt::Return {
+            span: self.span_from(token.span),
+            token,
+            expr: value,
+        })
     }
 
-    fn continue_statement(&mut self) -> Result<Stmt, String> {
-        self.consume(TokenType::SEMICOLON, "Expected ; after break")?;
-        Ok(Stmt::Continue)
+    fn continue_statement(&mut self) -> Result<Stmt, Diagnostic> {
+        let token = self.previous();
+        self.consume(TokenType::SEMICOLON, "Expected ; after continue")?;
+        Ok(Stmt::Continue {
+            span: self.span_from(token.span),
+            token,
+        })
     }
 
-    fn break_stmt(&mut self) -> Result<Stmt, String> {
+    fn break_stmt(&mut self) -> Result<Stmt, Diagnostic> {
+        let token = self.previous();
         self.consume(TokenType::SEMICOLON, "Expected ; after break")?;
-        Ok(Stmt::Break)
+        Ok(Stmt::Break {
+            span: self.span_from(token.span),
+            token,
+        })
     }
 
-    fn for_statement(&mut self) -> Result<Stmt, String> {
+    fn for_statement(&mut self) -> Result<Stmt, Diagnostic> {
         // expr statemet | var var_declaration
+        let start = self.previous().span;
         self.consume(TokenType::LEFT_PAREN, "Expected '('  after for")?;
         let statement_declaration = if self.match_tokens(&[TokenType::SEMICOLON]) {
             None
-        } else if self.match_tokens(&[TokenType::INT]) {
+        } else if self.match_tokens(&TYPES) {
             Some(self.var_declaration()?)
         } else {
             Some(self.expression_stmt()?)
@@ -170,35 +241,31 @@ impl Parser {
         let mut body;
         self.consume(TokenType::RIGHT_PAREN, "expected ')' after clause")?;
         self.consume(TokenType::LEFT_BRACE, "expected '{' after for clause")?;
-        let Stmt::Block { mut stmts } = self.block()? else {
-            return Err("Expeccted a block".to_string());
-        };
-        if !increment.is_none() {
-            stmts.push(Stmt::Block {
-                stmts: vec![Stmt::Expression {
-                    expression: increment.unwrap(),
-                }],
-            });
-        }
+        let block = self.block()?;
         if condition.is_none() {
             condition = Some(Expr::Literal {
                 value: LiteralValue::True,
+                span: start,
             })
         }
         body = Stmt::WHILE {
             condition: condition.unwrap(),
-            block: Box::new(Stmt::Block { stmts }),
+            block: Box::new(block),
+            increment,
+            span: self.span_from(start),
         };
         if let Some(init) = statement_declaration {
             Ok(Stmt::Block {
                 stmts: vec![init, body],
+                span: self.span_from(start),
             })
         } else {
             Ok(body)
         }
     }
 
-    fn while_stmt(&mut self) -> Result<Stmt, String> {
+    fn while_stmt(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.previous().span;
         self.consume(TokenType::LEFT_PAREN, "( Expected after while")?;
         let expr = self.expression()?;
         self.consume(TokenType::RIGHT_PAREN, ") Expected after statement")?;
@@ -207,10 +274,13 @@ impl Parser {
         Ok(Stmt::WHILE {
             condition: expr,
             block: Box::new(block),
+            increment: None,
+            span: self.span_from(start),
         })
     }
 
-    fn if_statement(&mut self) -> Result<Stmt, String> {
+    fn if_statement(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.previous().span;
         self.consume(TokenType::LEFT_PAREN, "Expected '(' after if")?;
         let expression = self.expression()?;
         self.consume(TokenType::RIGHT_PAREN, "Expected ')' after expression")?;
@@ -227,59 +297,77 @@ impl Parser {
             condition: expression,
             then: block,
             els: els_stmt,
+            span: self.span_from(start),
         })
     }
 
-    fn block(&mut self) -> Result<Stmt, String> {
+    fn block(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.previous().span;
         let mut statements = vec![];
         while !self.check(&TokenType::RIGHT_BRACE) && !self.is_at_end() {
-            statements.push(self.declaration()?);
+            if let Some(declaration) = self.declaration() {
+                statements.push(declaration);
+            }
         }
         self.consume(TokenType::RIGHT_BRACE, "Expected '}' after the block")?;
-        Ok(Stmt::Block { stmts: statements })
+        Ok(Stmt::Block {
+            stmts: statements,
+            span: self.span_from(start),
+        })
     }
 
-    fn print_stmt(&mut self) -> Result<Stmt, String> {
+    fn print_stmt(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.previous().span;
         self.consume(TokenType::LEFT_PAREN, "Expected '(' before value")?;
         let expr = self.expression()?;
         self.consume(TokenType::RIGHT_PAREN, "Expected ')' after value")?;
         self.consume(TokenType::SEMICOLON, "Expected after value';' ")?;
 
-        Ok(Stmt::Print { expression: expr })
+        Ok(Stmt::Print {
+            expression: expr,
+            span: self.span_from(start),
+        })
     }
 
-    fn expression_stmt(&mut self) -> Result<Stmt, String> {
+    fn expression_stmt(&mut self) -> Result<Stmt, Diagnostic> {
         let expr = self.expression()?;
         self.consume(TokenType::SEMICOLON, "Expected  ';' after expression")?;
-        Ok(Stmt::Expression { expression: expr })
+        Ok(Stmt::Expression {
+            span: self.span_from(expr.span()),
+            expression: expr,
+        })
     }
 
-    fn expression(&mut self) -> Result<Expr, String> {
+    fn expression(&mut self) -> Result<Expr, Diagnostic> {
         self.assignment()
     }
 
-    fn assignment(&mut self) -> Result<Expr, String> {
+    fn assignment(&mut self) -> Result<Expr, Diagnostic> {
         let expr = self.or()?;
         if self.match_tokens(&[TokenType::EQUAL]) {
             let value = self.assignment()?;
             match expr {
-                Expr::Variable { name } => Ok(Expr::Assign {
+                Expr::Variable { name, span } => Ok(Expr::Assign {
                     name,
+                    span: span.to(value.span()),
                     value: Box::from(value),
                 }),
-                _ => Err("Invalid assignment target".to_string()),
+                _ => Err(Diagnostic::error(expr.span(), "Invalid assignment target")
+                    .with_code(SYNTAX_ERROR)
+                    .with_label("cannot assign to this")),
             }
         } else {
             Ok(expr)
         }
     }
 
-    fn or(&mut self) -> Result<Expr, String> {
+    fn or(&mut self) -> Result<Expr, Diagnostic> {
         let mut expr = self.and()?;
         while self.match_tokens(&[TokenType::OR]) {
             let operator = self.previous();
             let right = self.and()?;
             expr = Expr::Logical {
+                span: expr.span().to(right.span()),
                 expression: Box::new(expr),
                 operator,
                 right: Box::new(right),
@@ -288,12 +376,13 @@ impl Parser {
         Ok(expr)
     }
 
-    fn and(&mut self) -> Result<Expr, String> {
+    fn and(&mut self) -> Result<Expr, Diagnostic> {
         let mut expr = self.equality()?;
         while self.match_tokens(&[TokenType::AND]) {
             let operator = self.previous();
             let right = self.equality()?;
             expr = Expr::Logical {
+                span: expr.span().to(right.span()),
                 expression: Box::new(expr),
                 operator,
                 right: Box::new(right),
@@ -302,12 +391,13 @@ impl Parser {
         Ok(expr)
     }
 
-    fn equality(&mut self) -> Result<Expr, String> {
+    fn equality(&mut self) -> Result<Expr, Diagnostic> {
         let mut expr = self.comparision()?;
         while self.match_tokens(&[TokenType::BANG_EQUAL, TokenType::EQUAL_EQUAL]) {
             let operator = self.previous();
             let right = self.comparision()?;
             expr = Expr::Binary {
+                span: expr.span().to(right.span()),
                 left: Box::from(expr),
                 operator,
                 right: Box::from(right),
@@ -315,29 +405,45 @@ impl Parser {
         }
         Ok(expr)
     }
+    // Skips tokens until the start of the next statement. Blocks opened while
+    // skipping are skipped whole, and a '}' closing the enclosing block is left
+    // in place so that block still ends where it should.
     fn syncronize(&mut self) {
-        self.advance();
+        let mut depth = 0;
         while !self.is_at_end() {
-            if self.previous().token_type == TokenType::SEMICOLON {
-                return;
-            }
             match self.peek().token_type {
+                TokenType::LEFT_BRACE => depth += 1,
+                TokenType::RIGHT_BRACE if depth == 0 => return,
+                TokenType::RIGHT_BRACE => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.advance();
+                        return;
+                    }
+                }
+                TokenType::SEMICOLON if depth == 0 => {
+                    self.advance();
+                    return;
+                }
+                // type names are left out, they also show up in the middle of
+                // function signatures
                 TokenType::CLASS
                 | TokenType::FUN
-                | TokenType::FLOAT
-                | TokenType::STRING
-                | TokenType::BOOL
                 | TokenType::FOR
                 | TokenType::IF
                 | TokenType::WHILE
                 | TokenType::PRINT
-                | TokenType::RETURN => return,
+                | TokenType::RETURN
+                    if depth == 0 =>
+                {
+                    return;
+                }
                 _ => (),
             }
             self.advance();
         }
     }
-    fn comparision(&mut self) -> Result<Expr, String> {
+    fn comparision(&mut self) -> Result<Expr, Diagnostic> {
         let mut expr = self.term()?;
 
         while self.match_tokens(&[
@@ -349,6 +455,7 @@ impl Parser {
             let operator = self.previous();
             let right = self.term()?;
             expr = Expr::Binary {
+                span: expr.span().to(right.span()),
                 left: Box::from(expr),
                 operator,
                 right: Box::from(right),
@@ -356,13 +463,14 @@ impl Parser {
         }
         Ok(expr)
     }
-    fn term(&mut self) -> Result<Expr, String> {
+    fn term(&mut self) -> Result<Expr, Diagnostic> {
         let mut expr = self.factor()?;
 
         while self.match_tokens(&[TokenType::MINUS, TokenType::PLUS]) {
             let operator = self.previous();
             let right = self.factor()?;
             expr = Expr::Binary {
+                span: expr.span().to(right.span()),
                 left: Box::from(expr),
                 operator,
                 right: Box::from(right),
@@ -371,13 +479,14 @@ impl Parser {
         Ok(expr)
     }
 
-    fn factor(&mut self) -> Result<Expr, String> {
-        let mut expr = self.unary()?;
+    fn factor(&mut self) -> Result<Expr, Diagnostic> {
+        let mut expr = self.cast()?;
 
         while self.match_tokens(&[TokenType::STAR, TokenType::SLASH, TokenType::Modulus]) {
             let operator = self.previous();
-            let right = self.unary()?;
+            let right = self.cast()?;
             expr = Expr::Binary {
+                span: expr.span().to(right.span()),
                 left: Box::from(expr),
                 operator,
                 right: Box::from(right),
@@ -386,11 +495,31 @@ impl Parser {
         Ok(expr)
     }
 
-    fn unary(&mut self) -> Result<Expr, String> {
+    // `as` binds tighter than the binary operators but looser than unary ones,
+    // so `-x as int` converts `-x`
+    fn cast(&mut self) -> Result<Expr, Diagnostic> {
+        let mut expr = self.unary()?;
+
+        while self.match_tokens(&[TokenType::AS]) {
+            let keyword = self.previous();
+            self.type_annotation("Expected type after 'as'")?;
+            let target = self.previous();
+            expr = Expr::Cast {
+                span: expr.span().to(target.span),
+                expression: Box::from(expr),
+                keyword,
+                target,
+            }
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, Diagnostic> {
         if self.match_tokens(&[TokenType::BANG, TokenType::MINUS]) {
             let operator = self.previous();
             let right = self.unary()?;
             Ok(Expr::Unary {
+                span: operator.span.to(right.span()),
                 operator,
                 right: Box::from(right),
             })
@@ -399,7 +528,7 @@ impl Parser {
         }
     }
 
-    fn call(&mut self) -> Result<Expr, String> {
+    fn call(&mut self) -> Result<Expr, Diagnostic> {
         let mut expr = self.primary()?;
 
         loop {
@@ -412,12 +541,16 @@ impl Parser {
         Ok(expr)
     }
 
-    fn finishCall(&mut self, callie: Expr) -> Result<Expr, String> {
+    fn finishCall(&mut self, callie: Expr) -> Result<Expr, Diagnostic> {
         let mut args = vec![];
         if !self.check(&TokenType::RIGHT_PAREN) {
             loop {
                 if args.len() >= 255 {
-                    return Err("Can't have more than 255 args".to_string());
+                    return Err(Diagnostic::error(
+                        self.peek().span,
+                        "Can't have more than 255 args",
+                    )
+                    .with_code(SYNTAX_ERROR));
                 }
                 args.push(self.expression()?);
                 if !self.match_tokens(&[TokenType::COMMA]) {
@@ -425,16 +558,16 @@ impl Parser {
                 }
             }
         }
-        let token = self.previous();
         let token = self.consume(TokenType::RIGHT_PAREN, "Expected ')' after args")?;
         Ok(Expr::Call {
+            span: callie.span().to(token.span),
             callie: Box::new(callie),
             paren: token,
             args,
         })
     }
 
-    fn primary(&mut self) -> Result<Expr, String> {
+    fn primary(&mut self) -> Result<Expr, Diagnostic> {
         let token = self.peek();
         let result;
         match token.token_type {
@@ -444,8 +577,13 @@ impl Parser {
                 self.consume(TokenType::RIGHT_PAREN, "Expected ')'")?;
                 result = Expr::Grouping {
                     expression: Box::from(expr),
+                    span: self.span_from(token.span),
                 }
             }
+            TokenType::STRINGLIT if matches!(token.literal, Some(Literal::Interpolated(_))) => {
+                self.advance();
+                result = self.interpolated(token)?;
+            }
             TokenType::FALSE
             | TokenType::TRUE
             | TokenType::NIL
@@ -453,6 +591,7 @@ impl Parser {
             | TokenType::STRINGLIT => {
                 self.advance();
                 result = Expr::Literal {
+                    span: token.span,
                     value: LiteralValue::from_token(token),
                 }
             }
@@ -460,31 +599,73 @@ impl Parser {
                 self.advance();
                 result = Expr::Variable {
                     name: self.previous(),
+                    span: token.span,
                 }
             }
             _ => {
-                let token = self.peek();
-                return Err(format!(
-                    "[line {}]  Expected expression found {}",
-                    token.line, token.lexeme
-                ));
+                return Err(self.error(&token, "Expected expression"));
             }
         }
 
         Ok(result)
     }
 
-    fn consume(&mut self, token_type: TokenType, msg: &str) -> Result<Token, String> {
+    // each `${...}` of an interpolated string literal is parsed on its own
+    fn interpolated(&self, token: Token) -> Result<Expr, Diagnostic> {
+        let Some(Literal::Interpolated(parts)) = token.literal else {
+            unreachable!("only called for interpolated strings");
+        };
+        let mut exprs = vec![];
+        for part in parts {
+            match part {
+                StringPart::Text(text) if text.is_empty() => (),
+                StringPart::Text(text) => exprs.push(Expr::Literal {
+                    value: LiteralValue::StringValue(text),
+                    span: token.span,
+                }),
+                StringPart::Code(tokens) => {
+                    let mut parser = Parser::new(tokens);
+                    let expr = parser.expression()?;
+                    if !parser.is_at_end() {
+                        return Err(parser
+                            .error(&parser.peek(), "Expected '}' after interpolated expression"));
+                    }
+                    exprs.push(expr);
+                }
+            }
+        }
+        Ok(Expr::Interpolated {
+            parts: exprs,
+            span: token.span,
+        })
+    }
+
+    fn consume(&mut self, token_type: TokenType, msg: &str) -> Result<Token, Diagnostic> {
         let token = self.peek();
         if token.token_type == token_type {
             self.advance();
             let token = self.previous();
             Ok(token)
         } else {
-            return Err(format!("{} at line {}", msg.to_string(), token.line));
+            return Err(self.error(&token, msg));
         }
     }
 
+    fn error(&self, token: &Token, msg: &str) -> Diagnostic {
+        let found = match token.token_type {
+            TokenType::EOF if token.lexeme.is_empty() => "found end of file".to_string(),
+            _ => format!("found '{}'", token.lexeme),
+        };
+        Diagnostic::error(token.span, msg)
+            .with_code(SYNTAX_ERROR)
+            .with_label(found)
+    }
+
+    // span from `start` up to and including the last consumed token
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.previous().span)
+    }
+
     fn match_tokens(&mut self, token_type: &[TokenType]) -> bool {
         for i in token_type {
             if self.check(i) {
@@ -521,66 +702,78 @@ impl Parser {
         self.tokens.get(self.current - 1 as usize).unwrap().clone()
     }
 }
-/*
 #[cfg(test)]
 mod tests {
-    use crate::scanner::{self, Scanner};
-
     use super::*;
+    use crate::lexer::Scanner;
 
-    #[test]
-    fn test_addition() {
-        let one = Token {
-            token_type: TokenType::NUMBER,
-            lexeme: "1".to_string(),
-            literal: Some(Literal::FLiteral(1.0)),
-            line: 0,
-        };
+    fn parse(source: &str) -> Vec<Stmt> {
+        let tokens = Scanner::new(source.to_string()).scanTokens().unwrap();
+        Parser::new(tokens).parse().unwrap()
+    }
 
-        let plus = Token {
-            token_type: TokenType::PLUS,
-            lexeme: "+".to_string(),
-            literal: None,
-            line: 0,
-        };
-        let two = Token {
-            token_type: TokenType::NUMBER,
-            lexeme: "2".to_string(),
-            literal: Some(Literal::FLiteral(2.0)),
-            line: 0,
-        };
-        let semicoln = Token {
-            token_type: TokenType::SEMICOLON,
-            lexeme: ";".to_string(),
-            literal: None,
-            line: 0,
-        };
-        let tokens = vec![one, plus, two, semicoln];
+    // the single expression statement `source` parses to, in prefix notation
+    fn parse_expression(source: &str) -> String {
+        match parse(source).as_slice() {
+            [Stmt::Expression { expression, .. }] => expression.to_string(),
+            statements => panic!("expected one expression statement, got {:?}", statements),
+        }
+    }
 
-        let mut parser = Parser::new(tokens);
-        let parsed = parser.parse();
-        let pstr = parsed.unwrap().to_string();
-        println!(": {}", pstr);
-        assert_eq!(pstr, "(+ 1 2)");
+    #[test]
+    fn test_addition() {
+        assert_eq!(parse_expression("1 + 2;"), "(+ 1 2)");
     }
 
     #[test]
     fn comparision() {
-        let source = "1 + 2 == 5 + 7".to_string();
-        let scan = Scanner::new(source);
-        let token = scan.scanTokens();
-        let mut parser = Parser::new(token);
-        let parsed_eq = parser.parse().unwrap().to_string();
-        assert_eq!(parsed_eq, "(== (+ 1 2) (+ 5 7))");
+        assert_eq!(parse_expression("1 + 2 == 5 + 7;"), "(== (+ 1 2) (+ 5 7))");
     }
+
     #[test]
     fn comparision_paren() {
-        let source = "1 == (2 + 2)".to_string();
-        let scan = Scanner::new(source);
-        let token = scan.scanTokens();
-        let mut parser = Parser::new(token);
-        let parsed_eq = parser.parse().unwrap().to_string();
-        println!("{}", parsed_eq);
-        assert_eq!(parsed_eq, "(== 1 (group (+ 2 2)))");
-    }
-}*/
+        assert_eq!(parse_expression("1 == (2 + 2);"), "(== 1 (group (+ 2 2)))");
+    }
+
+    fn doc(statement: &Stmt) -> Option<&str> {
+        match statement {
+            Stmt::Function { doc, .. } | Stmt::Var { doc, .. } => doc.as_deref(),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn doc_comment_on_function() {
+        let statements = parse(
+            "/// Adds one.\n///\n///   Keeps indentation.\nfun inc(int n) -> int { return n + 1; }",
+        );
+        assert_eq!(
+            doc(&statements[0]),
+            Some("Adds one.\n\n  Keeps indentation.")
+        );
+    }
+
+    #[test]
+    fn doc_comment_on_vars() {
+        let statements = parse(
+            "/// the limit\nint limit = 3;\nfor (/// the counter\nint i = 0; i < limit; i = i + 1) {}",
+        );
+        assert_eq!(doc(&statements[0]), Some("the limit"));
+        // `for` desugars to a block holding the initializer and the loop
+        let Stmt::Block { stmts, .. } = &statements[1] else {
+            panic!("expected the desugared for loop, got {:?}", statements[1]);
+        };
+        assert_eq!(doc(&stmts[0]), Some("the counter"));
+    }
+
+    #[test]
+    fn doc_comment_stays_with_the_next_statement() {
+        let statements = parse(
+            "/// not about x\nprint(1);\nint x = 2;\n//// ordinary comment\nint y = 3;\n/* /* nested */ */ fun f() {}",
+        );
+        assert_eq!(statements.len(), 4);
+        assert_eq!(doc(&statements[1]), None);
+        assert_eq!(doc(&statements[2]), None);
+        assert_eq!(doc(&statements[3]), None);
+    }
+}
@@ -0,0 +1,159 @@
+use std::io::{BufRead, Write, stdin, stdout};
+
+use inkwell::{OptimizationLevel, context::Context};
+
+use crate::{
+    checker::Checker,
+    cli,
+    codegen::Compiler,
+    diagnostic::{Diagnostic, UNTERMINATED_STRING},
+    jit,
+    lexer::Scanner,
+    parser::Parser,
+    token::Token,
+    tokentype::TokenType,
+};
+
+const PATH: &str = "repl";
+
+/// Reads statements from stdin and runs each entry as soon as it is complete.
+///
+/// Every entry is compiled into a module of its own and added to one JIT, the
+/// checker and compiler carry the names declared so far from entry to entry.
+pub fn run() -> i32 {
+    let context = Context::create();
+    let mut compiler = Compiler::new(&context, "repl");
+    let engine = match jit::create_engine(&compiler.module, OptimizationLevel::None) {
+        Ok(engine) => engine,
+        Err(e) => {
+            eprintln!("error: failed to start the JIT: {}", e);
+            return cli::EXIT_INTERNAL;
+        }
+    };
+    let mut checker = Checker::new();
+
+    // every accepted entry so far, new input is scanned after it so spans
+    // (and the lines diagnostics point at) stay valid across entries
+    let mut history = String::new();
+    let mut input = String::new();
+    let mut lines = stdin().lock().lines();
+    loop {
+        print!("{}", if input.is_empty() { "> " } else { ". " });
+        let _ = stdout().flush();
+        let Some(Ok(line)) = lines.next() else {
+            println!();
+            return 0;
+        };
+        input.push_str(&line);
+        input.push('\n');
+
+        let Some(tokens) = scan_entry(&history, &mut input) else {
+            continue;
+        };
+        let source = format!("{}{}", history, input);
+        if tokens.len() == 1 {
+            // nothing but whitespace and comments
+            history = source;
+            input.clear();
+            continue;
+        }
+
+        let mut statements = match Parser::new(tokens).parse() {
+            Ok(statements) => statements,
+            Err(errors) => {
+                report(&errors, &source);
+                input.clear();
+                continue;
+            }
+        };
+
+        let before = checker.clone();
+        let diagnostics = checker.check_entry(&mut statements);
+        report(&diagnostics, &source);
+        if diagnostics.iter().any(Diagnostic::is_error) {
+            checker = before;
+            input.clear();
+            continue;
+        }
+
+        let name = match compiler.generate_entry(statements) {
+            Ok(name) => name,
+            Err(error) => {
+                report(&[error], &source);
+                // the compiler dropped the entry, the checker has to forget it too
+                checker = before;
+                input.clear();
+                continue;
+            }
+        };
+        if engine.add_module(&compiler.module).is_err() {
+            eprintln!("error: failed to add entry to the JIT");
+            return cli::EXIT_INTERNAL;
+        }
+        jit::map_runtime(&engine, &compiler.module);
+        match unsafe { engine.get_function::<unsafe extern "C" fn()>(&name) } {
+            Ok(entry) => unsafe { entry.call() },
+            Err(e) => {
+                eprintln!("error: failed to run entry: {}", e);
+                return cli::EXIT_INTERNAL;
+            }
+        }
+        jit::flush_output();
+
+        history = source;
+        input.clear();
+    }
+}
+
+// Scans `input` as a continuation of `history`, returning only the new
+// tokens, or None when the entry continues on the next line. A missing ';'
+// after the last statement is added to `input`.
+fn scan_entry(history: &str, input: &mut String) -> Option<Vec<Token>> {
+    let tokens = match scan(history, input) {
+        Ok(tokens) => tokens,
+        Err(errors) => {
+            if errors.iter().any(|e| e.code == Some(UNTERMINATED_STRING)) {
+                return None;
+            }
+            report(&errors, &format!("{}{}", history, input));
+            input.clear();
+            return None;
+        }
+    };
+
+    let depth: i32 = tokens
+        .iter()
+        .map(|token| match token.token_type {
+            TokenType::LEFT_BRACE => 1,
+            TokenType::RIGHT_BRACE => -1,
+            _ => 0,
+        })
+        .sum();
+    if depth > 0 {
+        return None;
+    }
+
+    let last = tokens.iter().rev().nth(1).map(|token| token.token_type);
+    match last {
+        None | Some(TokenType::SEMICOLON) | Some(TokenType::RIGHT_BRACE) => Some(tokens),
+        Some(_) => {
+            input.truncate(input.trim_end().len());
+            input.push_str(";\n");
+            scan(history, input).ok()
+        }
+    }
+}
+
+fn scan(history: &str, input: &str) -> Result<Vec<Token>, Vec<Diagnostic>> {
+    let tokens = Scanner::new(format!("{}{}", history, input)).scanTokens()?;
+    Ok(tokens
+        .into_iter()
+        .filter(|token| token.span.start >= history.len() || token.token_type == TokenType::EOF)
+        .collect())
+}
+
+fn report(diagnostics: &[Diagnostic], source: &str) {
+    for diagnostic in diagnostics {
+        eprintln!("{}", diagnostic.render(PATH, source));
+    }
+}
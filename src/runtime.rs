@@ -0,0 +1,375 @@
+//! Runtime support the generated code calls into.
+//!
+//! Strings are `{ i8*, i64 }` pairs of a pointer to their bytes and their
+//! length. String literals point into constant globals, strings built while
+//! the program runs live on the heap and are never freed.
+//!
+//! The helpers are generated as LLVM IR into whichever module first needs
+//! them, so JIT-compiled code, linked executables and every REPL entry get
+//! them without a separate runtime library. They only rely on the C library.
+
+use inkwell::{
+    AddressSpace, IntPredicate,
+    builder::Builder,
+    context::Context,
+    module::{Linkage, Module},
+    types::{BasicMetadataTypeEnum, FunctionType, StructType},
+    values::{BasicMetadataValueEnum, BasicValueEnum, FunctionValue, StructValue},
+};
+
+const CONCAT: &str = "testlang.string_concat";
+const COMPARE: &str = "testlang.string_compare";
+const FROM_INT: &str = "testlang.string_from_int";
+const FROM_FLOAT: &str = "testlang.string_from_float";
+const IS_CHAR_BOUNDARY: &str = "testlang.is_char_boundary";
+
+pub fn string_type(context: &Context) -> StructType<'_> {
+    context.struct_type(
+        &[
+            context.ptr_type(AddressSpace::default()).into(),
+            context.i64_type().into(),
+        ],
+        false,
+    )
+}
+
+// the C library function `name`, declared in `module` on first use
+pub fn libc_function<'ctx>(
+    module: &Module<'ctx>,
+    name: &str,
+    ty: FunctionType<'ctx>,
+) -> FunctionValue<'ctx> {
+    module
+        .get_function(name)
+        .unwrap_or_else(|| module.add_function(name, ty, None))
+}
+
+fn malloc<'ctx>(context: &'ctx Context, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+    let ptr_type = context.ptr_type(AddressSpace::default());
+    libc_function(
+        module,
+        "malloc",
+        ptr_type.fn_type(&[context.i64_type().into()], false),
+    )
+}
+
+fn memcpy<'ctx>(context: &'ctx Context, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+    let ptr_type = context.ptr_type(AddressSpace::default());
+    libc_function(
+        module,
+        "memcpy",
+        ptr_type.fn_type(
+            &[ptr_type.into(), ptr_type.into(), context.i64_type().into()],
+            false,
+        ),
+    )
+}
+
+// Starts the definition of the helper `name`, or hands back the existing
+// function when `module` already has it.
+fn define<'ctx>(
+    context: &'ctx Context,
+    module: &Module<'ctx>,
+    name: &str,
+    ty: FunctionType<'ctx>,
+) -> Result<(FunctionValue<'ctx>, Builder<'ctx>), FunctionValue<'ctx>> {
+    if let Some(function) = module.get_function(name) {
+        return Err(function);
+    }
+    let function = module.add_function(name, ty, Some(Linkage::Internal));
+    let builder = context.create_builder();
+    builder.position_at_end(context.append_basic_block(function, "entry"));
+    Ok((function, builder))
+}
+
+/// `string_concat(a, b)` returns a new string holding `a` followed by `b`.
+pub fn concat<'ctx>(context: &'ctx Context, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+    let string = string_type(context);
+    let ty = string.fn_type(&[string.into(), string.into()], false);
+    let (function, builder) = match define(context, module, CONCAT, ty) {
+        Ok(definition) => definition,
+        Err(function) => return function,
+    };
+    let malloc = malloc(context, module);
+    let memcpy = memcpy(context, module);
+
+    let a = function.get_nth_param(0).unwrap().into_struct_value();
+    let b = function.get_nth_param(1).unwrap().into_struct_value();
+    let a_ptr = builder.build_extract_value(a, 0, "a_ptr").unwrap();
+    let a_len = builder.build_extract_value(a, 1, "a_len").unwrap();
+    let b_ptr = builder.build_extract_value(b, 0, "b_ptr").unwrap();
+    let b_len = builder.build_extract_value(b, 1, "b_len").unwrap();
+    let len = builder
+        .build_int_add(a_len.into_int_value(), b_len.into_int_value(), "len")
+        .unwrap();
+
+    let bytes = builder
+        .build_call(malloc, &[len.into()], "bytes")
+        .unwrap()
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_pointer_value();
+    builder
+        .build_call(memcpy, &[bytes.into(), a_ptr.into(), a_len.into()], "")
+        .unwrap();
+    let rest = unsafe {
+        builder
+            .build_gep(context.i8_type(), bytes, &[a_len.into_int_value()], "rest")
+            .unwrap()
+    };
+    builder
+        .build_call(memcpy, &[rest.into(), b_ptr.into(), b_len.into()], "")
+        .unwrap();
+
+    let result = build_string(&builder, string, bytes.into(), len.into());
+    builder.build_return(Some(&result)).unwrap();
+    function
+}
+
+/// `string_compare(a, b)` orders `a` and `b` byte by byte like strcmp: the
+/// result is negative, zero or positive when `a` sorts before, equal to or
+/// after `b`.
+pub fn compare<'ctx>(context: &'ctx Context, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+    let string = string_type(context);
+    let i32_type = context.i32_type();
+    let i64_type = context.i64_type();
+    let ty = i32_type.fn_type(&[string.into(), string.into()], false);
+    let (function, builder) = match define(context, module, COMPARE, ty) {
+        Ok(definition) => definition,
+        Err(function) => return function,
+    };
+    let ptr_type = context.ptr_type(AddressSpace::default());
+    let memcmp = libc_function(
+        module,
+        "memcmp",
+        i32_type.fn_type(&[ptr_type.into(), ptr_type.into(), i64_type.into()], false),
+    );
+
+    let a = function.get_nth_param(0).unwrap().into_struct_value();
+    let b = function.get_nth_param(1).unwrap().into_struct_value();
+    let a_ptr = builder.build_extract_value(a, 0, "a_ptr").unwrap();
+    let a_len = builder
+        .build_extract_value(a, 1, "a_len")
+        .unwrap()
+        .into_int_value();
+    let b_ptr = builder.build_extract_value(b, 0, "b_ptr").unwrap();
+    let b_len = builder
+        .build_extract_value(b, 1, "b_len")
+        .unwrap()
+        .into_int_value();
+
+    let a_shorter = builder
+        .build_int_compare(IntPredicate::ULT, a_len, b_len, "a_shorter")
+        .unwrap();
+    let common = builder
+        .build_select(a_shorter, a_len, b_len, "common")
+        .unwrap();
+    let order = builder
+        .build_call(
+            memcmp,
+            &[a_ptr.into(), b_ptr.into(), common.into()],
+            "order",
+        )
+        .unwrap()
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_int_value();
+
+    // when the common prefix is equal the shorter string comes first
+    let a_longer = builder
+        .build_int_compare(IntPredicate::UGT, a_len, b_len, "a_longer")
+        .unwrap();
+    let by_length = builder
+        .build_select(
+            a_shorter,
+            i32_type.const_all_ones(),
+            builder
+                .build_select(
+                    a_longer,
+                    i32_type.const_int(1, false),
+                    i32_type.const_zero(),
+                    "longer",
+                )
+                .unwrap()
+                .into_int_value(),
+            "by_length",
+        )
+        .unwrap();
+    let prefix_differs = builder
+        .build_int_compare(IntPredicate::NE, order, i32_type.const_zero(), "differs")
+        .unwrap();
+    let result = builder
+        .build_select(prefix_differs, order.into(), by_length, "result")
+        .unwrap();
+    builder.build_return(Some(&result)).unwrap();
+    function
+}
+
+/// `is_char_boundary(s, i)` tells whether byte `i` of `s` starts a UTF-8
+/// character, or is the end of `s`. `i` must be within `0..=len`.
+pub fn is_char_boundary<'ctx>(
+    context: &'ctx Context,
+    module: &Module<'ctx>,
+) -> FunctionValue<'ctx> {
+    let string = string_type(context);
+    let i64_type = context.i64_type();
+    let bool_type = context.bool_type();
+    let ty = bool_type.fn_type(&[string.into(), i64_type.into()], false);
+    let (function, builder) = match define(context, module, IS_CHAR_BOUNDARY, ty) {
+        Ok(definition) => definition,
+        Err(function) => return function,
+    };
+
+    let s = function.get_nth_param(0).unwrap().into_struct_value();
+    let index = function.get_nth_param(1).unwrap().into_int_value();
+    let ptr = builder
+        .build_extract_value(s, 0, "ptr")
+        .unwrap()
+        .into_pointer_value();
+    let len = builder
+        .build_extract_value(s, 1, "len")
+        .unwrap()
+        .into_int_value();
+    let at_end = builder
+        .build_int_compare(IntPredicate::EQ, index, len, "at_end")
+        .unwrap();
+    // the end has no byte to look at, heap strings aren't NUL terminated
+    let inside = context.append_basic_block(function, "inside");
+    let end = context.append_basic_block(function, "end");
+    builder
+        .build_conditional_branch(at_end, end, inside)
+        .unwrap();
+
+    builder.position_at_end(end);
+    builder
+        .build_return(Some(&bool_type.const_all_ones()))
+        .unwrap();
+
+    builder.position_at_end(inside);
+    let byte_ptr = unsafe {
+        builder
+            .build_gep(context.i8_type(), ptr, &[index], "byte_ptr")
+            .unwrap()
+    };
+    let byte = builder
+        .build_load(context.i8_type(), byte_ptr, "byte")
+        .unwrap()
+        .into_int_value();
+    // continuation bytes look like 0b10xxxxxx
+    let i8_type = context.i8_type();
+    let high_bits = builder
+        .build_and(byte, i8_type.const_int(0xC0, false), "high_bits")
+        .unwrap();
+    let starts_char = builder
+        .build_int_compare(
+            IntPredicate::NE,
+            high_bits,
+            i8_type.const_int(0x80, false),
+            "starts_char",
+        )
+        .unwrap();
+    builder.build_return(Some(&starts_char)).unwrap();
+    function
+}
+
+/// `string_from_int(n)` formats `n` the way `print` does.
+pub fn from_int<'ctx>(context: &'ctx Context, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+    format_function(context, module, FROM_INT, context.i64_type().into(), "%lld")
+}
+
+/// `string_from_float(x)` formats `x` the way `print` does.
+pub fn from_float<'ctx>(context: &'ctx Context, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+    format_function(context, module, FROM_FLOAT, context.f64_type().into(), "%f")
+}
+
+// Defines `name` as a function that formats its one argument of type `param`
+// with `format` into a new string.
+fn format_function<'ctx>(
+    context: &'ctx Context,
+    module: &Module<'ctx>,
+    name: &str,
+    param: BasicMetadataTypeEnum<'ctx>,
+    format: &str,
+) -> FunctionValue<'ctx> {
+    let string = string_type(context);
+    let (function, builder) = match define(context, module, name, string.fn_type(&[param], false)) {
+        Ok(definition) => definition,
+        Err(function) => return function,
+    };
+    let value = function.get_nth_param(0).unwrap();
+    let result = build_format(context, module, &builder, format, &[value.into()]);
+    builder.build_return(Some(&result)).unwrap();
+    function
+}
+
+/// Emits code at `builder` that formats `args` with the printf style
+/// `format` into a new string, measuring it with a first snprintf call.
+pub fn build_format<'ctx>(
+    context: &'ctx Context,
+    module: &Module<'ctx>,
+    builder: &Builder<'ctx>,
+    format: &str,
+    args: &[BasicMetadataValueEnum<'ctx>],
+) -> StructValue<'ctx> {
+    let i32_type = context.i32_type();
+    let i64_type = context.i64_type();
+    let ptr_type = context.ptr_type(AddressSpace::default());
+    let snprintf = libc_function(
+        module,
+        "snprintf",
+        i32_type.fn_type(&[ptr_type.into(), i64_type.into(), ptr_type.into()], true),
+    );
+    let malloc = malloc(context, module);
+
+    let format = builder
+        .build_global_string_ptr(format, "format")
+        .unwrap()
+        .as_pointer_value();
+    let mut measure = vec![
+        ptr_type.const_null().into(),
+        i64_type.const_zero().into(),
+        format.into(),
+    ];
+    measure.extend_from_slice(args);
+    let len = builder
+        .build_call(snprintf, &measure, "len")
+        .unwrap()
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_int_value();
+    let len = builder.build_int_s_extend(len, i64_type, "len").unwrap();
+    // snprintf always writes the terminating NUL, leave room for it
+    let size = builder
+        .build_int_add(len, i64_type.const_int(1, false), "size")
+        .unwrap();
+    let bytes = builder
+        .build_call(malloc, &[size.into()], "bytes")
+        .unwrap()
+        .try_as_basic_value()
+        .left()
+        .unwrap();
+    let mut write = vec![bytes.into(), size.into(), format.into()];
+    write.extend_from_slice(args);
+    builder.build_call(snprintf, &write, "").unwrap();
+
+    build_string(builder, string_type(context), bytes, len.into())
+}
+
+// assembles a string value from its bytes and length
+pub fn build_string<'ctx>(
+    builder: &Builder<'ctx>,
+    string: StructType<'ctx>,
+    bytes: BasicValueEnum<'ctx>,
+    len: BasicValueEnum<'ctx>,
+) -> StructValue<'ctx> {
+    let value = builder
+        .build_insert_value(string.get_undef(), bytes, 0, "string")
+        .unwrap();
+    builder
+        .build_insert_value(value, len, 1, "string")
+        .unwrap()
+        .into_struct_value()
+}
@@ -1,38 +1,80 @@
-use crate::{expr::Expr, token::Token, tokentype::TokenType};
+use crate::{
+    expr::Expr,
+    token::{Span, Token},
+    tokentype::TokenType,
+};
 #[derive(Clone, Debug)]
 pub enum Stmt {
     Expression {
         expression: Expr,
+        span: Span,
     },
     Print {
         expression: Expr,
+        span: Span,
     },
     Var {
         name: Token,
         data_type: TokenType,
         initializer: Expr,
+        // the `///` comment above the declaration
+        doc: Option<String>,
+        span: Span,
     },
     Block {
         stmts: Vec<Stmt>,
+        span: Span,
     },
     IfElse {
         condition: Expr,
         then: Box<Stmt>,
         els: Option<Box<Stmt>>,
+        span: Span,
     },
     WHILE {
         condition: Expr,
         block: Box<Stmt>,
+        // set for loops desugared from `for`, runs after the body and on `continue`
+        increment: Option<Expr>,
+        span: Span,
     },
     Function {
         name: Token,
-        params: Vec<Token>,
+        params: Vec<(Token, TokenType)>,
+        return_type: TokenType,
         body: Vec<Stmt>,
+        // the `///` comment above the declaration
+        doc: Option<String>,
+        span: Span,
     },
     Return {
         token: Token,
         expr: Option<Expr>,
+        span: Span,
     },
-    Break,
-    Continue,
+    Break {
+        token: Token,
+        span: Span,
+    },
+    Continue {
+        token: Token,
+        span: Span,
+    },
+}
+
+impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Expression { span, .. }
+            | Stmt::Print { span, .. }
+            | Stmt::Var { span, .. }
+            | Stmt::Block { span, .. }
+            | Stmt::IfElse { span, .. }
+            | Stmt::WHILE { span, .. }
+            | Stmt::Function { span, .. }
+            | Stmt::Return { span, .. }
+            | Stmt::Break { span, .. }
+            | Stmt::Continue { span, .. } => *span,
+        }
+    }
 }
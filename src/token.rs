@@ -1,21 +1,67 @@
-use std::fmt::format;
+use std::fmt::{Display, format};
 
 use crate::tokentype::TokenType;
 
 #[derive(Debug, Clone)]
 pub enum Literal {
     StringLiteral(String),
+    // a string literal containing `${...}`
+    Interpolated(Vec<StringPart>),
     FLiteral(f64),
     ILiteral(i64),
     IdentifierLiteral(String),
 }
 
+#[derive(Debug, Clone)]
+pub enum StringPart {
+    Text(String),
+    // the tokens between `${` and `}`, followed by an EOF token at the `}`
+    Code(Vec<Token>),
+}
+
+// Location of a token or AST node in the source. `start`/`end` are byte
+// offsets, `line` and `column` (both 1-based) describe where it starts.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(line: usize, column: usize, start: usize, end: usize) -> Self {
+        Self {
+            line,
+            column,
+            start,
+            end,
+        }
+    }
+
+    // covers everything from the start of `self` to the end of `other`
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: other.end.max(self.end),
+            ..self
+        }
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
     pub literal: Option<Literal>,
-    pub line: usize,
+    pub span: Span,
+    // the `///` comment right before the token, its lines joined with '\n'
+    pub doc: Option<String>,
 }
 
 impl ToString for Token {
@@ -28,12 +74,13 @@ impl ToString for Token {
 }
 
 impl Token {
-    pub fn new(t_type: TokenType, lexeme: String, literal: Option<Literal>, line: usize) -> Self {
+    pub fn new(t_type: TokenType, lexeme: String, literal: Option<Literal>, span: Span) -> Self {
         Self {
             token_type: t_type,
             lexeme,
             literal,
-            line,
+            span,
+            doc: None,
         }
     }
 }
@@ -22,6 +22,7 @@ pub enum TokenType {
     GREATER_EQUAL,
     LESS,
     LESS_EQUAL,
+    ARROW,
     FLOAT,
     // Literals.
     IDENTIFIER,
@@ -29,6 +30,7 @@ pub enum TokenType {
     NUMBER,
     // Keywords.
     AND,
+    AS,
     CLASS,
     ELSE,
     FALSE,
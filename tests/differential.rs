@@ -0,0 +1,109 @@
+//! Runs every program in `tests/programs/` through the LLVM backend (both
+//! JIT-compiled and as a linked executable) and through the AST interpreter,
+//! and fails when their stdout or exit codes disagree.
+//!
+//! Needs the `llvm` feature, without it there is only one backend to run.
+#![cfg(feature = "llvm")]
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+const TESTLANG: &str = env!("CARGO_BIN_EXE_testlang");
+
+const EXIT_COMPILE_ERROR: i32 = 65;
+// exit code `build` uses when there is no linker on this machine
+const EXIT_UNAVAILABLE: i32 = 69;
+
+fn programs() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/programs");
+    let mut programs: Vec<PathBuf> = fs::read_dir(&dir)
+        .expect("tests/programs should exist")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lox"))
+        .collect();
+    programs.sort();
+    programs
+}
+
+fn testlang(args: &[&str]) -> Output {
+    Command::new(TESTLANG)
+        .args(args)
+        .output()
+        .expect("failed to start testlang")
+}
+
+// (exit code, stdout) of running `program` with every available backend, labelled
+fn run_everywhere(program: &Path) -> Vec<(&'static str, Option<i32>, String)> {
+    let path = program.to_str().unwrap();
+    let mut results = vec![];
+    for (label, args) in [
+        ("jit", vec!["run", path]),
+        ("interp", vec!["run", "--backend=interp", path]),
+    ] {
+        let output = testlang(&args);
+        results.push((
+            label,
+            output.status.code(),
+            String::from_utf8_lossy(&output.stdout).into_owned(),
+        ));
+    }
+
+    let name = program.file_stem().unwrap().to_str().unwrap();
+    let exe = env::temp_dir().join(format!("testlang-diff-{}-{}", std::process::id(), name));
+    let build = testlang(&["build", path, "-o", exe.to_str().unwrap()]);
+    match build.status.code() {
+        Some(0) => {
+            let output = Command::new(&exe).output().expect("failed to run executable");
+            let _ = fs::remove_file(&exe);
+            results.push((
+                "exe",
+                output.status.code(),
+                String::from_utf8_lossy(&output.stdout).into_owned(),
+            ));
+        }
+        Some(EXIT_UNAVAILABLE) => (),
+        _ => results.push((
+            "exe",
+            build.status.code(),
+            String::from_utf8_lossy(&build.stderr).into_owned(),
+        )),
+    }
+    results
+}
+
+#[test]
+fn backends_agree_on_every_program() {
+    let programs = programs();
+    assert!(!programs.is_empty(), "no programs found in tests/programs");
+
+    let mut failures = vec![];
+    for program in &programs {
+        let results = run_everywhere(program);
+        let (expected_label, expected_code, expected_out) = &results[0];
+        // backends agreeing that a program doesn't compile proves nothing
+        if *expected_code == Some(EXIT_COMPILE_ERROR) {
+            failures.push(format!("{}: does not compile", program.display()));
+            continue;
+        }
+        for (label, code, out) in &results[1..] {
+            if code != expected_code || out != expected_out {
+                failures.push(format!(
+                    "{}: {} and {} disagree\n--- {} (exit {:?})\n{}--- {} (exit {:?})\n{}",
+                    program.display(),
+                    expected_label,
+                    label,
+                    expected_label,
+                    expected_code,
+                    expected_out,
+                    label,
+                    code,
+                    out
+                ));
+            }
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
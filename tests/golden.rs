@@ -0,0 +1,209 @@
+//! Golden-file tests: every `.lox` file under `tests/golden/` is run and
+//! checked against the expectations written in its comments.
+//!
+//! - `// expect: <line>` lines, in order, are the program's stdout.
+//! - `// error: <message>` on a line means compiling the program reports an
+//!   error at that line whose message contains `<message>`.
+//!
+//! Run `cargo test --test golden -- --bless` to rewrite the expectations from
+//! what the programs currently do. Other arguments filter files by name.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Command, ExitCode},
+};
+
+const TESTLANG: &str = env!("CARGO_BIN_EXE_testlang");
+const EXPECT: &str = "// expect: ";
+const ERROR: &str = "// error: ";
+
+struct Outcome {
+    stdout: Vec<String>,
+    // (line, message) of every error diagnostic
+    errors: Vec<(usize, String)>,
+    code: Option<i32>,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let bless = args.iter().any(|arg| arg == "--bless");
+    let filters: Vec<&String> = args.iter().filter(|arg| !arg.starts_with('-')).collect();
+
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let mut files: Vec<PathBuf> = fs::read_dir(&dir)
+        .expect("tests/golden should exist")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lox"))
+        .filter(|path| {
+            let name = path.to_string_lossy();
+            filters.is_empty() || filters.iter().any(|filter| name.contains(filter.as_str()))
+        })
+        .collect();
+    files.sort();
+
+    let mut failed = 0;
+    for file in &files {
+        let source = fs::read_to_string(file).unwrap();
+        let outcome = run(file);
+        if bless {
+            fs::write(file, bless_source(&source, &outcome)).unwrap();
+            println!("blessed {}", file.display());
+            continue;
+        }
+        match compare(&source, &outcome) {
+            Ok(()) => println!("test {} ... ok", file.display()),
+            Err(msg) => {
+                failed += 1;
+                println!("test {} ... FAILED\n{}", file.display(), msg);
+            }
+        }
+    }
+
+    println!(
+        "\ngolden: {} passed; {} failed",
+        files.len() - failed,
+        failed
+    );
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn run(file: &Path) -> Outcome {
+    let output = Command::new(TESTLANG)
+        .arg("run")
+        .arg(file)
+        .output()
+        .expect("failed to start testlang");
+    let stdout = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(String::from)
+        .collect();
+
+    // diagnostics look like "error[E0102]: <message>" followed by " --> <path>:<line>:<col>"
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let mut errors = vec![];
+    let mut lines = stderr.lines();
+    while let Some(line) = lines.next() {
+        let Some(header) = line.strip_prefix("error") else {
+            continue;
+        };
+        let message = match header.find("]: ") {
+            Some(i) if header.starts_with('[') => &header[i + 3..],
+            _ => header.trim_start_matches(": "),
+        };
+        let line_number = lines
+            .next()
+            .and_then(|location| location.rsplit(':').nth(1))
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(0);
+        errors.push((line_number, message.to_string()));
+    }
+
+    Outcome {
+        stdout,
+        errors,
+        code: output.status.code(),
+    }
+}
+
+fn compare(source: &str, outcome: &Outcome) -> Result<(), String> {
+    let mut expected_out = vec![];
+    let mut expected_errors = vec![];
+    for (i, line) in source.lines().enumerate() {
+        if let Some(at) = line.find(EXPECT) {
+            expected_out.push(line[at + EXPECT.len()..].to_string());
+        }
+        if let Some(at) = line.find(ERROR) {
+            expected_errors.push((i + 1, line[at + ERROR.len()..].trim_end().to_string()));
+        }
+    }
+
+    let mut problems = vec![];
+    for (line, message) in &expected_errors {
+        let found = outcome
+            .errors
+            .iter()
+            .any(|(l, m)| l == line && m.contains(message.as_str()));
+        if !found {
+            problems.push(format!("missing error at line {}: {}", line, message));
+        }
+    }
+    for (line, message) in &outcome.errors {
+        let expected = expected_errors
+            .iter()
+            .any(|(l, m)| l == line && message.contains(m.as_str()));
+        if !expected {
+            problems.push(format!("unexpected error at line {}: {}", line, message));
+        }
+    }
+    if expected_errors.is_empty() && outcome.code != Some(0) {
+        problems.push(format!("exited with {:?}", outcome.code));
+    }
+    if outcome.stdout != expected_out {
+        problems.push(format!(
+            "stdout differs\n--- expected\n{}\n--- actual\n{}",
+            expected_out.join("\n"),
+            outcome.stdout.join("\n")
+        ));
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems.join("\n"))
+    }
+}
+
+// Rewrites the expectations in `source` to match `outcome`. Errors go on the
+// line they were reported at. Output lines replace the existing `expect`
+// comments in place when there are as many of them, and are otherwise
+// appended to the end of the file.
+fn bless_source(source: &str, outcome: &Outcome) -> String {
+    let mut lines: Vec<String> = source.lines().map(strip_error).collect();
+
+    let expect_lines: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| line.contains(EXPECT))
+        .map(|(i, _)| i)
+        .collect();
+    if expect_lines.len() == outcome.stdout.len() {
+        for (i, out) in expect_lines.iter().zip(&outcome.stdout) {
+            let at = lines[*i].find(EXPECT).unwrap();
+            lines[*i] = format!("{}{}{}", &lines[*i][..at], EXPECT, out);
+        }
+    } else {
+        lines = lines
+            .into_iter()
+            .filter_map(|line| match line.find(EXPECT) {
+                Some(0) => None,
+                Some(at) => Some(line[..at].trim_end().to_string()),
+                None => Some(line),
+            })
+            .collect();
+        for out in &outcome.stdout {
+            lines.push(format!("{}{}", EXPECT, out));
+        }
+    }
+
+    for (line, message) in &outcome.errors {
+        if let Some(text) = line.checked_sub(1).and_then(|i| lines.get_mut(i)) {
+            text.push_str(&format!(" {}{}", ERROR, message));
+        }
+    }
+
+    let mut blessed = lines.join("\n");
+    blessed.push('\n');
+    blessed
+}
+
+fn strip_error(line: &str) -> String {
+    match line.find(ERROR) {
+        Some(at) => line[..at].trim_end().to_string(),
+        None => line.to_string(),
+    }
+}